@@ -101,7 +101,7 @@ def preprocess_stages(data_dir):
             l_folder_type = split_path[1]
             l_file_name = split_path[-1]
             if l_folder_type == "dat":
-                if l_file_name.endswith(".plc"):
+                if l_file_name.endswith(".plc") or l_file_name.endswith(".bzs"):
                     plc_dat.append((l_file_name, l_arc.get_file_data(l_path)))
             elif l_folder_type == "dzb":
                 dzb_dat.append((l_file_name, l_arc.get_file_data(l_path)))
@@ -117,7 +117,7 @@ def preprocess_stages(data_dir):
                     r_folder_type = split_path[1]
                     r_file_name = split_path[-1]
                     if r_folder_type == "dat":
-                        if r_file_name.endswith(".plc"):
+                        if r_file_name.endswith(".plc") or r_file_name.endswith(".bzs"):
                             r_dat.append((r_file_name, r_arc.get_file_data(r_path)))
                     elif r_folder_type == "kcl":
                         r_kcl.append((r_file_name, r_arc.get_file_data(r_path)))
@@ -183,7 +183,8 @@ def preprocess_stages(data_dir):
         
         # new layout will be
         #   <stage>
-        #     stage
+        #     stage.bzs
+        #     addon
         #       dzb/plc pairing
         #     rooms
         #       r##
@@ -194,7 +195,9 @@ def preprocess_stages(data_dir):
         curr_dir = output_dir / stage / "addon"
         os.makedirs(curr_dir, exist_ok=True)
         for stage_files in (dzb_dat + plc_dat):
-            with open(curr_dir / stage_files[0], "wb") as f:
+            # stage.bzs belongs to the stage itself, not the hidden addon collision
+            file_dir = output_dir / stage if stage_files[0].endswith(".bzs") else curr_dir
+            with open(file_dir / stage_files[0], "wb") as f:
                 f.write(stage_files[1])          
 
         curr_dir = output_dir / stage / "rooms"
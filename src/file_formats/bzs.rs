@@ -1,20 +1,16 @@
-use binrw::{binrw, BinRead, NullString};
+use binrw::{binread, binrw, BinRead, BinWrite, NullString};
 use byteorder::{BigEndian, ReadBytesExt};
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
-struct SizedString<const SIZE: usize> {
-    #[br(temp)]
-    #[bw(calc = self.str.as_bytes().try_into().unwrap())]
-    __buffer: [u8; SIZE],
+#[derive(Debug, Clone)]
+pub struct SizedString<const SIZE: usize> {
+    // The whole buffer is kept so the bytes after the terminator are written back unchanged
+    buffer: [u8; SIZE],
     #[br(calc = {
-        if let Some(i) = __buffer.iter().position(|&a| a == b'\0') {
-            String::from_utf8(__buffer[0..i].to_vec()).expect("Should be a valid string")
-        } else {
-            String::from_utf8(__buffer.to_vec()).expect("Should be a valid string")
-        }
+        let len = buffer.iter().position(|&a| a == b'\0').unwrap_or(SIZE);
+        String::from_utf8_lossy(&buffer[0..len]).to_string()
     })]
     #[bw(ignore)]
     str: String,
@@ -22,309 +18,320 @@ struct SizedString<const SIZE: usize> {
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FILE {
-    unk: u16,
-    dummy: u16,
+    pub unk: u16,
+    pub dummy: u16,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RMPLData {
-    data: u16,
+    pub data: u16,
 }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug)]
+// The data of each entry is stored after the table of all entries, so it can only be read here
+#[binread]
+#[br(big)]
+#[derive(Debug, Clone)]
 pub struct RMPL {
-    id: u8,
+    pub id: u8,
     #[br(temp)]
-    #[bw(calc = (self.data.len() & 0xFF).try_into().unwrap())]
     count: u8,
+    // Offset from the start of this entry
+    #[br(temp)]
+    offset: u16,
 
-    #[br(restore_position, count = count)]
-    data: Vec<RMPLData>,
+    #[br(seek_before = SeekFrom::Current(offset as i64 - 4), restore_position, count = count)]
+    pub data: Vec<RMPLData>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SCEN {
-    name: SizedString<32>,
-    room: u8,
-    layer: u8,
-    entrance: u8,
-    night: u8,
-    byte5: u8,
-    flag6: u8,
-    zero: u8,
-    save_prompt: u8,
+    pub name: SizedString<32>,
+    pub room: u8,
+    pub layer: u8,
+    pub entrance: u8,
+    pub night: u8,
+    pub byte5: u8,
+    pub flag6: u8,
+    pub zero: u8,
+    pub save_prompt: u8,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CAM {
-    unk1: [u8; 4],
-    pos: [f32; 3],
-    angle: f32,
-    unk2: [u8; 8],
-    name: SizedString<16>,
+    pub unk1: [u8; 4],
+    pub pos: [f32; 3],
+    pub angle: f32,
+    pub unk2: [u8; 8],
+    pub name: SizedString<16>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PCAM {
-    pos1: [f32; 3],
-    pos2: [f32; 3],
-    angle: f32,
-    unkf: f32,
-    unk: [u8; 4],
+    pub pos1: [f32; 3],
+    pub pos2: [f32; 3],
+    pub angle: f32,
+    pub unkf: f32,
+    pub unk: [u8; 4],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PATH {
-    unk1: u8,
-    unk2: u8,
-    pnt_start_idx: u16,
-    pnt_total_count: u16,
-    unk3: [u8; 6],
+    pub unk1: u8,
+    pub unk2: u8,
+    pub pnt_start_idx: u16,
+    pub pnt_total_count: u16,
+    pub unk3: [u8; 6],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SPTH {
-    unk1: u8,
-    unk2: u8,
-    pnt_start_idx: u16,
-    pnt_total_count: u16,
-    unk3: [u8; 6],
+    pub unk1: u8,
+    pub unk2: u8,
+    pub pnt_start_idx: u16,
+    pub pnt_total_count: u16,
+    pub unk3: [u8; 6],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PNT {
-    pos: [f32; 3],
-    unk: [u8; 4],
+    pub pos: [f32; 3],
+    pub unk: [u8; 4],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SPNT {
-    pos: [f32; 3],
-    unk: [u8; 4],
+    pub pos: [f32; 3],
+    pub unk: [u8; 4],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BPNT {
-    pos1: [f32; 3],
-    pos2: [f32; 3],
-    pos3: [f32; 3],
-    unk: [u8; 4],
+    pub pos1: [f32; 3],
+    pub pos2: [f32; 3],
+    pub pos3: [f32; 3],
+    pub unk: [u8; 4],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AREA {
-    pos: [f32; 3],
-    size: [f32; 3],
-    angle: u16,
-    area_link: i16,
-    unk3: u8,
+    pub pos: [f32; 3],
+    pub size: [f32; 3],
+    pub angle: u16,
+    pub area_link: i16,
+    pub unk3: u8,
     _pad: [u8; 3],
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EVNT {
-    unk1: [u8; 2],
-    story_flag1: i16,
-    story_flag2: i16,
-    unk2: [u8; 3],
-    exit_id: u8,
-    unk3: [u8; 3],
-    skipevent: u8,
-    unk4: u8,
-    sceneflag1: u8,
-    sceneflag2: u8,
-    skipflag: u8,
-    dummy1: i16,
-    item: i16,
-    dummy2: i16,
-    name: SizedString<32>,
+    pub unk1: [u8; 2],
+    pub story_flag1: i16,
+    pub story_flag2: i16,
+    pub unk2: [u8; 3],
+    pub exit_id: u8,
+    pub unk3: [u8; 3],
+    pub skipevent: u8,
+    pub unk4: u8,
+    pub sceneflag1: u8,
+    pub sceneflag2: u8,
+    pub skipflag: u8,
+    pub dummy1: i16,
+    pub item: i16,
+    pub dummy2: i16,
+    pub name: SizedString<32>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PLY {
-    storyflag: i16,
-    play_cutscene: u8,
-    byte4: u8,
-    pos: [f32; 3],
-    angle: [i16; 3],
-    entrance_id: i16,
+    pub storyflag: i16,
+    pub play_cutscene: u8,
+    pub byte4: u8,
+    pub pos: [f32; 3],
+    pub angle: [i16; 3],
+    pub entrance_id: i16,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct STAG {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    size: [f32; 3],
-    angle: [u16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub size: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LYSE {
-    story_flag: i16,
-    night: u8,
-    layer: u8,
+    pub story_flag: i16,
+    pub night: u8,
+    pub layer: u8,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct STIF {
-    wtf: [f32; 3],
-    byte1: u8,
-    flag_index: u8,
-    byte3: u8,
-    byte4: u8,
-    unk1: [u8; 2],
-    map_name_id: u8,
-    unk2: u8,
+    pub wtf: [f32; 3],
+    pub byte1: u8,
+    pub flag_index: u8,
+    pub byte3: u8,
+    pub byte4: u8,
+    pub unk1: [u8; 2],
+    pub map_name_id: u8,
+    pub unk2: u8,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LYLT {
-    layer: u8,
-    demo_high: u8,
-    demo_low: u8,
-    dummy: u8,
+    pub layer: u8,
+    pub demo_high: u8,
+    pub demo_low: u8,
+    pub dummy: u8,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SOBJ {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    size: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub size: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SOBS {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    size: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub size: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OBJ {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OBJS {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct STAS {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    size: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub size: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SNDT {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    size: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub size: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DOOR {
-    params1: u32,
-    params2: u32,
-    pos: [f32; 3],
-    angle: [i16; 3],
-    id: u16,
-    name: SizedString<8>,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: [f32; 3],
+    pub angle: [i16; 3],
+    pub id: u16,
+    pub name: SizedString<8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct OBJN {
-    name: String,
+    pub name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct ARCN {
-    name: String,
+    pub name: String,
+}
+
+/// A chunk whose magic is not known. The data is kept as is, up to the start of the next chunk
+#[derive(Debug, Clone)]
+pub struct UnknownChunk {
+    pub magic: [u8; 4],
+    pub count: u16,
+    pub data: Vec<u8>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct LayerData {
     sobj: Vec<SOBJ>,
     sobs: Vec<SOBS>,
@@ -336,9 +343,12 @@ pub struct LayerData {
     door: Vec<DOOR>,
     objn: Vec<OBJN>,
     arcn: Vec<ARCN>,
+    unknown: Vec<UnknownChunk>,
+    // The magic of every chunk in the order they were read
+    chunk_order: Vec<[u8; 4]>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct BZS {
     file: Vec<FILE>,
     stif: Vec<STIF>,
@@ -356,24 +366,152 @@ pub struct BZS {
     ply_: Vec<PLY>,
     lyse: Vec<LYSE>,
     lylt: Vec<LYLT>,
-    sobj: Vec<SOBJ>,
-    sobs: Vec<SOBS>,
-    layers: [LayerData; 29],
+    // Objects outside of the layers (SOBJ, SOBS, ...)
+    objects: LayerData,
+    layers: [LayerData; LAYER_COUNT],
+    unknown: Vec<UnknownChunk>,
+    // The magic of every chunk in the order they were read
+    chunk_order: Vec<[u8; 4]>,
 }
 
+pub const LAYER_COUNT: usize = 29;
+
 #[binrw]
 #[brw(big)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Node {
     count: u16,  // Number of Entries in the Data
     _pad: u16,   // Padding
     offset: u32, // Data Offset
 }
 
-impl BZS {
-    pub fn read<T: Clone + Read + Seek>(
+// An entry of a chunk table (magic + Node) with the offset resolved
+struct Chunk {
+    magic: [u8; 4],
+    count: u16,
+    data_pos: u64,
+}
+
+// Reads a table of `count` chunks at the current position of the reader
+fn read_chunk_table<T: Read + Seek>(
+    reader: &mut T,
+    count: u16,
+) -> Result<Vec<Chunk>, Box<dyn std::error::Error>> {
+    let mut chunks = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        // The offset is relative to the start of the entry
+        let entry_pos = reader.stream_position()?;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let node = Node::read(reader)?;
+        chunks.push(Chunk {
+            magic,
+            count: node.count,
+            data_pos: entry_pos + node.offset as u64,
+        });
+    }
+    Ok(chunks)
+}
+
+// Reads the data of a chunk that is not understood. It spans until the data of the next chunk or `end`
+fn read_unknown_chunk<T: Read + Seek>(
+    reader: &mut T,
+    chunk: &Chunk,
+    chunks: &[Chunk],
+    end: u64,
+) -> Result<UnknownChunk, Box<dyn std::error::Error>> {
+    let next = chunks
+        .iter()
+        .map(|c| c.data_pos)
+        .filter(|&pos| pos > chunk.data_pos)
+        .min()
+        .unwrap_or(end)
+        .min(end);
+
+    let mut data = vec![0u8; next.saturating_sub(chunk.data_pos) as usize];
+    reader.seek(SeekFrom::Start(chunk.data_pos))?;
+    reader.read_exact(&mut data)?;
+
+    Ok(UnknownChunk {
+        magic: chunk.magic,
+        count: chunk.count,
+        data,
+    })
+}
+
+impl LayerData {
+    // Reads the chunk into the matching list. Returns false when the magic does not belong to a layer
+    fn read_chunk<T: Read + Seek>(
+        &mut self,
         reader: &mut T,
+        chunk: &Chunk,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        reader.seek(SeekFrom::Start(chunk.data_pos))?;
+        for _ in 0..chunk.count {
+            match &chunk.magic {
+                b"SOBJ" => self.sobj.push(SOBJ::read(reader)?),
+                b"SOBS" => self.sobs.push(SOBS::read(reader)?),
+                b"OBJ " => self.obj_.push(OBJ::read(reader)?),
+                b"OBJS" => self.objs.push(OBJS::read(reader)?),
+                b"STAS" => self.stas.push(STAS::read(reader)?),
+                b"STAG" => self.stag.push(STAG::read(reader)?),
+                b"SNDT" => self.sndt.push(SNDT::read(reader)?),
+                b"DOOR" => self.door.push(DOOR::read(reader)?),
+                b"OBJN" | b"ARCN" => {
+                    // u16 offset from the start of the first entry...
+                    let offset = reader.read_u16::<BigEndian>()?;
+
+                    // Pos to return to after reading nullterm string
+                    let pos = reader.stream_position()?;
+
+                    // read string
+                    reader.seek(SeekFrom::Start(chunk.data_pos + offset as u64))?;
+
+                    let string = NullString::read(reader)?;
+
+                    // return reader to pos
+                    reader.seek(SeekFrom::Start(pos))?;
+
+                    // Insert into layer
+                    if &chunk.magic == b"OBJN" {
+                        self.objn.push(OBJN {
+                            name: string.to_string(),
+                        });
+                    } else {
+                        self.arcn.push(ARCN {
+                            name: string.to_string(),
+                        });
+                    }
+                }
+                _ => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    fn read<T: Read + Seek>(
+        reader: &mut T,
+        count: u16,
+        end: u64,
     ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut layer = Self::default();
+
+        let chunks = read_chunk_table(reader, count)?;
+        for chunk in &chunks {
+            layer.chunk_order.push(chunk.magic);
+            if !layer.read_chunk(reader, chunk)? {
+                layer
+                    .unknown
+                    .push(read_unknown_chunk(reader, chunk, &chunks, end)?);
+            }
+        }
+
+        Ok(layer)
+    }
+}
+
+impl BZS {
+    pub fn read<T: Read + Seek>(reader: &mut T) -> Result<Self, Box<dyn std::error::Error>> {
         // Reading BZS Starts with a root node of V001
         let mut magic = [0u8; 4];
 
@@ -384,116 +522,78 @@ impl BZS {
         }
         let root = Node::read(reader)?;
 
+        // Unknown chunks can at most span until the end of the file
+        let end = reader.seek(SeekFrom::End(0))?;
+
         reader.seek(SeekFrom::Start(root.offset.into()))?;
 
         let mut bzs = Self::default();
 
-        for _ in 0..root.count {
-            // Create a Sub Reader to read the node data while keeping the main readers position
-            let sub_reader = &mut reader.clone();
-
-            // read the node magic
-            reader.read_exact(&mut magic)?;
-            let node = Node::read(reader)?;
-
-            // Make sure sub_reader is used here as it now points to the data
-            sub_reader.seek_relative(node.offset.into())?;
-            for i in 0..node.count {
-                match &magic {
-                    b"FILE" => bzs.file.push(FILE::read(sub_reader)?),
-                    b"RMPL" => bzs.rmpl.push(RMPL::read(sub_reader)?),
-                    b"SCEN" => bzs.scen.push(SCEN::read(sub_reader)?),
-                    b"CAM " => bzs.cam_.push(CAM::read(sub_reader)?),
-                    b"PCAM" => bzs.pcam.push(PCAM::read(sub_reader)?),
-                    b"PATH" => bzs.path.push(PATH::read(sub_reader)?),
-                    b"SPTH" => bzs.spth.push(SPTH::read(sub_reader)?),
-                    b"PNT " => bzs.pnt_.push(PNT::read(sub_reader)?),
-                    b"SPNT" => bzs.spnt.push(SPNT::read(sub_reader)?),
-                    b"BPNT" => bzs.bpnt.push(BPNT::read(sub_reader)?),
-                    b"AREA" => bzs.area.push(AREA::read(sub_reader)?),
-                    b"EVNT" => bzs.evnt.push(EVNT::read(sub_reader)?),
-                    b"PLY " => bzs.ply_.push(PLY::read(sub_reader)?),
-                    b"LYSE" => bzs.lyse.push(LYSE::read(sub_reader)?),
-                    b"STIF" => bzs.stif.push(STIF::read(sub_reader)?),
-                    b"SOBJ" => bzs.sobj.push(SOBJ::read(sub_reader)?),
-                    b"SOBS" => bzs.sobs.push(SOBS::read(sub_reader)?),
-                    b"LYLT" => bzs.lylt.push(LYLT::read(sub_reader)?),
-                    b"LAY " => {
-                        // Must Read 29 layers
-                        let mut layer_mag = [0u8; 4];
-                        if node.count != 29 {
-                            return Err("\"LAY \": does not contain 29 entries".into());
-                        }
-                        let layer: &mut LayerData = bzs.layers.get_mut(i as usize).unwrap();
-
-                        // Create a Sub Reader to read the node data while keeping the main readers position
-                        let lay_reader = &mut sub_reader.clone();
-                        let layer_node = Node::read(sub_reader)?;
-
-                        // Make sure sub_reader is used here as it now points to the data
-                        lay_reader.seek_relative(layer_node.offset.into())?;
-
-                        for _ in 0..layer_node.count {
-                            // Reading Layer Nodes now :)
-                            let sub_reader = &mut lay_reader.clone();
-
-                            // read the node magic
-                            lay_reader.read_exact(&mut layer_mag)?;
-                            let node = Node::read(lay_reader)?;
-
-                            // Make sure sub_reader is used here as it now points to the data
-                            sub_reader.seek_relative(node.offset.into())?;
-
-                            // Used for ARCN/OBJN
-                            let objn_arcn_pos: u64 = sub_reader.stream_position()?;
-
-                            for _ in 0..node.count {
-                                match &layer_mag {
-                                    b"SOBJ" => layer.sobj.push(SOBJ::read(sub_reader)?),
-                                    b"SOBS" => layer.sobs.push(SOBS::read(sub_reader)?),
-                                    b"OBJ " => layer.obj_.push(OBJ::read(sub_reader)?),
-                                    b"OBJS" => layer.objs.push(OBJS::read(sub_reader)?),
-                                    b"STAS" => layer.stas.push(STAS::read(sub_reader)?),
-                                    b"STAG" => layer.stag.push(STAG::read(sub_reader)?),
-                                    b"SNDT" => layer.sndt.push(SNDT::read(sub_reader)?),
-                                    b"DOOR" => layer.door.push(DOOR::read(sub_reader)?),
-                                    b"OBJN" | b"ARCN" => {
-                                        // u16 offset from the start of the first entry...
-                                        let offset = sub_reader.read_u16::<BigEndian>()?;
-
-                                        // Pos to return to after reading nullterm string
-                                        let pos = sub_reader.stream_position()?;
-
-                                        // read string
-                                        sub_reader
-                                            .seek(SeekFrom::Start(objn_arcn_pos + offset as u64))?;
-
-                                        let string = NullString::read(sub_reader)?;
-
-                                        // return sub_reader to pos
-                                        sub_reader.seek(SeekFrom::Start(pos))?;
-
-                                        // Insert into layer
-                                        if &layer_mag == b"OBJN" {
-                                            layer.objn.push(OBJN {
-                                                name: string.to_string(),
-                                            });
-                                        } else {
-                                            layer.arcn.push(ARCN {
-                                                name: string.to_string(),
-                                            });
-                                        }
-                                    }
-                                    _ => println!("Unknown Magic: {}", unsafe {
-                                        String::from_utf8_unchecked(magic.to_vec())
-                                    }),
-                                }
-                            }
-                        }
+        let chunks = read_chunk_table(reader, root.count)?;
+        for chunk in &chunks {
+            bzs.chunk_order.push(chunk.magic);
+            reader.seek(SeekFrom::Start(chunk.data_pos))?;
+            for _ in 0..chunk.count {
+                match &chunk.magic {
+                    b"FILE" => bzs.file.push(FILE::read(reader)?),
+                    b"RMPL" => bzs.rmpl.push(RMPL::read(reader)?),
+                    b"SCEN" => bzs.scen.push(SCEN::read(reader)?),
+                    b"CAM " => bzs.cam_.push(CAM::read(reader)?),
+                    b"PCAM" => bzs.pcam.push(PCAM::read(reader)?),
+                    b"PATH" => bzs.path.push(PATH::read(reader)?),
+                    b"SPTH" => bzs.spth.push(SPTH::read(reader)?),
+                    b"PNT " => bzs.pnt_.push(PNT::read(reader)?),
+                    b"SPNT" => bzs.spnt.push(SPNT::read(reader)?),
+                    b"BPNT" => bzs.bpnt.push(BPNT::read(reader)?),
+                    b"AREA" => bzs.area.push(AREA::read(reader)?),
+                    b"EVNT" => bzs.evnt.push(EVNT::read(reader)?),
+                    b"PLY " => bzs.ply_.push(PLY::read(reader)?),
+                    b"LYSE" => bzs.lyse.push(LYSE::read(reader)?),
+                    b"STIF" => bzs.stif.push(STIF::read(reader)?),
+                    b"LYLT" => bzs.lylt.push(LYLT::read(reader)?),
+                    _ => break,
+                }
+            }
+
+            match &chunk.magic {
+                b"FILE" | b"RMPL" | b"SCEN" | b"CAM " | b"PCAM" | b"PATH" | b"SPTH" | b"PNT "
+                | b"SPNT" | b"BPNT" | b"AREA" | b"EVNT" | b"PLY " | b"LYSE" | b"STIF" | b"LYLT" => {
+                }
+                b"LAY " => {
+                    // Must Read 29 layers
+                    if chunk.count as usize != LAYER_COUNT {
+                        return Err("\"LAY \": does not contain 29 entries".into());
+                    }
+
+                    // The offset is relative to the start of the layer's entry
+                    let layer_starts: Vec<(u64, u16)> = (0..LAYER_COUNT as u64)
+                        .map(|i| {
+                            let entry_pos = chunk.data_pos + i * LAYER_ENTRY_SIZE as u64;
+                            reader.seek(SeekFrom::Start(entry_pos))?;
+                            let layer_node = Node::read(reader)?;
+                            Ok((entry_pos + layer_node.offset as u64, layer_node.count))
+                        })
+                        .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+
+                    for (i, &(start, count)) in layer_starts.iter().enumerate() {
+                        // Unknown chunks of a layer can at most span until the data following the layer
+                        let layer_end = chunks
+                            .iter()
+                            .map(|c| c.data_pos)
+                            .chain(layer_starts.iter().filter(|l| l.1 != 0).map(|l| l.0))
+                            .filter(|&pos| pos > start)
+                            .min()
+                            .unwrap_or(end);
+
+                        reader.seek(SeekFrom::Start(start))?;
+                        bzs.layers[i] = LayerData::read(reader, count, layer_end)?;
+                    }
+                }
+                _ => {
+                    if !bzs.objects.read_chunk(reader, chunk)? {
+                        bzs.unknown
+                            .push(read_unknown_chunk(reader, chunk, &chunks, end)?);
                     }
-                    _ => println!("Unknown Magic: {}", unsafe {
-                        String::from_utf8_unchecked(magic.to_vec())
-                    }),
                 }
             }
         }
@@ -501,3 +601,577 @@ impl BZS {
         Ok(bzs)
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                                  Writing                                                          //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+const NODE_PADDING: u16 = 0xFFFF;
+const ROOT_HEADER_SIZE: u32 = 0xC;
+const CHUNK_ENTRY_SIZE: usize = 0xC;
+const LAYER_ENTRY_SIZE: usize = 0x8;
+const RMPL_ENTRY_SIZE: usize = 0x4;
+const CHUNK_ALIGNMENT: usize = 0x4;
+const FILE_ALIGNMENT: usize = 0x20;
+
+// Where new chunks are placed when a section was not part of the file that was read
+const LAYER_CHUNKS: [[u8; 4]; 10] = [
+    *b"SOBS", *b"SOBJ", *b"STAS", *b"STAG", *b"SNDT", *b"OBJS", *b"OBJ ", *b"DOOR", *b"OBJN",
+    *b"ARCN",
+];
+const ROOT_CHUNKS: [[u8; 4]; 16] = [
+    *b"FILE", *b"SCEN", *b"CAM ", *b"PCAM", *b"PATH", *b"PNT ", *b"SPTH", *b"SPNT", *b"BPNT",
+    *b"AREA", *b"EVNT", *b"PLY ", *b"RMPL", *b"STIF", *b"LYSE", *b"LYLT",
+];
+
+// (count, data) of a section that is ready to be written
+type SectionData = (u16, Vec<u8>);
+// (magic, count, data) of a chunk that is ready to be written
+type ChunkData = ([u8; 4], u16, Vec<u8>);
+
+// Padding in bzs files is done with 0xFF
+fn pad(data: &mut Vec<u8>, alignment: usize) {
+    data.resize(data.len().next_multiple_of(alignment), 0xFF);
+}
+
+fn entry_count(len: usize) -> Result<u16, Box<dyn std::error::Error>> {
+    u16::try_from(len).map_err(|_| "Too many entries in a chunk".into())
+}
+
+fn write_entries<T>(entries: &[T]) -> Result<SectionData, Box<dyn std::error::Error>>
+where
+    T: for<'a> BinWrite<Args<'a> = ()>,
+{
+    let mut data = std::io::Cursor::new(Vec::new());
+    for entry in entries {
+        entry.write_be(&mut data)?;
+    }
+    Ok((entry_count(entries.len())?, data.into_inner()))
+}
+
+// OBJN/ARCN: a table of u16 offsets (from the start of the chunk data) followed by the null terminated strings
+fn write_names<'a>(
+    names: impl ExactSizeIterator<Item = &'a str>,
+) -> Result<SectionData, Box<dyn std::error::Error>> {
+    let count = entry_count(names.len())?;
+    let mut table = Vec::new();
+    let mut strings = Vec::new();
+    for name in names {
+        let offset = u16::try_from(count as usize * 2 + strings.len())
+            .map_err(|_| "Names do not fit in the chunk")?;
+        table.extend_from_slice(&offset.to_be_bytes());
+        strings.extend_from_slice(name.as_bytes());
+        strings.push(0);
+    }
+    table.extend(strings);
+    Ok((count, table))
+}
+
+// A table of 12 byte entries (magic + Node) followed by the data of each chunk, padded to 4
+fn write_chunk_table(chunks: &[ChunkData]) -> Vec<u8> {
+    let table_size = chunks.len() * CHUNK_ENTRY_SIZE;
+    let mut table = Vec::with_capacity(table_size);
+    let mut body = Vec::new();
+    for (i, (magic, count, data)) in chunks.iter().enumerate() {
+        // The offset is relative to the start of the entry
+        let offset = table_size + body.len() - i * CHUNK_ENTRY_SIZE;
+        table.extend_from_slice(magic);
+        table.extend_from_slice(&count.to_be_bytes());
+        table.extend_from_slice(&NODE_PADDING.to_be_bytes());
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+
+        body.extend_from_slice(data);
+        pad(&mut body, CHUNK_ALIGNMENT);
+    }
+    table.extend(body);
+    table
+}
+
+// Chunks are written in the order they were read, followed by the sections that were added since
+fn write_order(
+    read_order: &[[u8; 4]],
+    known_chunks: &[[u8; 4]],
+    has_data: impl Fn(&[u8; 4]) -> bool,
+) -> Vec<[u8; 4]> {
+    let mut order: Vec<[u8; 4]> = Vec::new();
+    for magic in read_order {
+        // A section holds the entries of every chunk with its magic, so it is only written once
+        if !known_chunks.contains(magic) || !order.contains(magic) {
+            order.push(*magic);
+        }
+    }
+    for magic in known_chunks {
+        if !order.contains(magic) && has_data(magic) {
+            order.push(*magic);
+        }
+    }
+    order
+}
+
+impl LayerData {
+    // The (count, data) of a section. None when the magic does not belong to a layer
+    fn chunk_data(
+        &self,
+        magic: &[u8; 4],
+    ) -> Result<Option<SectionData>, Box<dyn std::error::Error>> {
+        Ok(Some(match magic {
+            b"SOBJ" => write_entries(&self.sobj)?,
+            b"SOBS" => write_entries(&self.sobs)?,
+            b"OBJ " => write_entries(&self.obj_)?,
+            b"OBJS" => write_entries(&self.objs)?,
+            b"STAS" => write_entries(&self.stas)?,
+            b"STAG" => write_entries(&self.stag)?,
+            b"SNDT" => write_entries(&self.sndt)?,
+            b"DOOR" => write_entries(&self.door)?,
+            b"OBJN" => write_names(self.objn.iter().map(|n| n.name.as_str()))?,
+            b"ARCN" => write_names(self.arcn.iter().map(|n| n.name.as_str()))?,
+            _ => return Ok(None),
+        }))
+    }
+
+    fn has_data(&self, magic: &[u8; 4]) -> bool {
+        match magic {
+            b"SOBJ" => !self.sobj.is_empty(),
+            b"SOBS" => !self.sobs.is_empty(),
+            b"OBJ " => !self.obj_.is_empty(),
+            b"OBJS" => !self.objs.is_empty(),
+            b"STAS" => !self.stas.is_empty(),
+            b"STAG" => !self.stag.is_empty(),
+            b"SNDT" => !self.sndt.is_empty(),
+            b"DOOR" => !self.door.is_empty(),
+            b"OBJN" => !self.objn.is_empty(),
+            b"ARCN" => !self.arcn.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        write_order(&self.chunk_order, &LAYER_CHUNKS, |m| self.has_data(m)).is_empty()
+    }
+
+    fn chunks(&self) -> Result<Vec<ChunkData>, Box<dyn std::error::Error>> {
+        // Unknown chunks are stored in the order they were read
+        let mut unknown = self.unknown.iter();
+
+        let mut chunks = Vec::new();
+        for magic in write_order(&self.chunk_order, &LAYER_CHUNKS, |m| self.has_data(m)) {
+            if let Some((count, data)) = self.chunk_data(&magic)? {
+                chunks.push((magic, count, data));
+            } else if let Some(chunk) = unknown.next() {
+                chunks.push((chunk.magic, chunk.count, chunk.data.clone()));
+            }
+        }
+        Ok(chunks)
+    }
+}
+
+impl BZS {
+    // 29 entries of (count, padding, offset) followed by the chunk table of each layer
+    fn write_layers(&self) -> Result<SectionData, Box<dyn std::error::Error>> {
+        let table_size = LAYER_COUNT * LAYER_ENTRY_SIZE;
+        let mut table = Vec::with_capacity(table_size);
+        let mut body = Vec::new();
+        for (i, layer) in self.layers.iter().enumerate() {
+            let chunks = layer.chunks()?;
+
+            // Empty layers do not point to any data
+            let mut offset = 0;
+            if !chunks.is_empty() {
+                offset = table_size + body.len() - i * LAYER_ENTRY_SIZE;
+                body.extend(write_chunk_table(&chunks));
+                pad(&mut body, CHUNK_ALIGNMENT);
+            }
+            table.extend_from_slice(&entry_count(chunks.len())?.to_be_bytes());
+            table.extend_from_slice(&NODE_PADDING.to_be_bytes());
+            table.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+        table.extend(body);
+        Ok((LAYER_COUNT as u16, table))
+    }
+
+    // A table of (id, count, offset from the entry) followed by the room ids of each entry
+    fn write_rmpl(&self) -> Result<SectionData, Box<dyn std::error::Error>> {
+        let table_size = self.rmpl.len() * RMPL_ENTRY_SIZE;
+        let mut table = Vec::with_capacity(table_size);
+        let mut body = Vec::new();
+        for (i, rmpl) in self.rmpl.iter().enumerate() {
+            let count = u8::try_from(rmpl.data.len()).map_err(|_| "Too many RMPL entries")?;
+            let offset = u16::try_from(table_size + body.len() - i * RMPL_ENTRY_SIZE)
+                .map_err(|_| "RMPL does not fit in the chunk")?;
+            table.push(rmpl.id);
+            table.push(count);
+            table.extend_from_slice(&offset.to_be_bytes());
+            for data in &rmpl.data {
+                body.extend_from_slice(&data.data.to_be_bytes());
+            }
+        }
+        table.extend(body);
+        Ok((entry_count(self.rmpl.len())?, table))
+    }
+
+    // The (count, data) of a section. None when the magic is not known
+    fn chunk_data(
+        &self,
+        magic: &[u8; 4],
+    ) -> Result<Option<SectionData>, Box<dyn std::error::Error>> {
+        Ok(Some(match magic {
+            b"FILE" => write_entries(&self.file)?,
+            b"RMPL" => self.write_rmpl()?,
+            b"SCEN" => write_entries(&self.scen)?,
+            b"CAM " => write_entries(&self.cam_)?,
+            b"PCAM" => write_entries(&self.pcam)?,
+            b"PATH" => write_entries(&self.path)?,
+            b"SPTH" => write_entries(&self.spth)?,
+            b"PNT " => write_entries(&self.pnt_)?,
+            b"SPNT" => write_entries(&self.spnt)?,
+            b"BPNT" => write_entries(&self.bpnt)?,
+            b"AREA" => write_entries(&self.area)?,
+            b"EVNT" => write_entries(&self.evnt)?,
+            b"PLY " => write_entries(&self.ply_)?,
+            b"LYSE" => write_entries(&self.lyse)?,
+            b"STIF" => write_entries(&self.stif)?,
+            b"LYLT" => write_entries(&self.lylt)?,
+            b"LAY " => self.write_layers()?,
+            _ => return self.objects.chunk_data(magic),
+        }))
+    }
+
+    fn has_data(&self, magic: &[u8; 4]) -> bool {
+        match magic {
+            b"FILE" => !self.file.is_empty(),
+            b"RMPL" => !self.rmpl.is_empty(),
+            b"SCEN" => !self.scen.is_empty(),
+            b"CAM " => !self.cam_.is_empty(),
+            b"PCAM" => !self.pcam.is_empty(),
+            b"PATH" => !self.path.is_empty(),
+            b"SPTH" => !self.spth.is_empty(),
+            b"PNT " => !self.pnt_.is_empty(),
+            b"SPNT" => !self.spnt.is_empty(),
+            b"BPNT" => !self.bpnt.is_empty(),
+            b"AREA" => !self.area.is_empty(),
+            b"EVNT" => !self.evnt.is_empty(),
+            b"PLY " => !self.ply_.is_empty(),
+            b"LYSE" => !self.lyse.is_empty(),
+            b"STIF" => !self.stif.is_empty(),
+            b"LYLT" => !self.lylt.is_empty(),
+            b"LAY " => self.layers.iter().any(|l| !l.is_empty()),
+            _ => self.objects.has_data(magic),
+        }
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))] // Only the tests write stages back for now
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+        let known_chunks: Vec<[u8; 4]> = ROOT_CHUNKS
+            .iter()
+            .chain(&LAYER_CHUNKS)
+            .chain([b"LAY "])
+            .copied()
+            .collect();
+
+        let mut unknown = self.unknown.iter();
+        let mut chunks = Vec::new();
+        for magic in write_order(&self.chunk_order, &known_chunks, |m| self.has_data(m)) {
+            if let Some((count, data)) = self.chunk_data(&magic)? {
+                chunks.push((magic, count, data));
+            } else if let Some(chunk) = unknown.next() {
+                chunks.push((chunk.magic, chunk.count, chunk.data.clone()));
+            }
+        }
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"V001");
+        data.extend_from_slice(&entry_count(chunks.len())?.to_be_bytes());
+        data.extend_from_slice(&NODE_PADDING.to_be_bytes());
+        data.extend_from_slice(&ROOT_HEADER_SIZE.to_be_bytes());
+        data.extend(write_chunk_table(&chunks));
+        pad(&mut data, FILE_ALIGNMENT);
+
+        writer.write_all(&data)?;
+        Ok(())
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                                 Accessors                                                         //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[allow(dead_code)]
+impl<const SIZE: usize> SizedString<SIZE> {
+    /// Strings longer than SIZE are cut off
+    pub fn new(str: &str) -> Self {
+        let mut buffer = [0u8; SIZE];
+        let len = str.len().min(SIZE);
+        buffer[..len].copy_from_slice(&str.as_bytes()[..len]);
+        Self {
+            buffer,
+            str: String::from_utf8_lossy(&buffer[..len]).to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.str
+    }
+}
+
+#[allow(dead_code)]
+impl LayerData {
+    pub fn sobj(&self) -> &[SOBJ] {
+        &self.sobj
+    }
+    pub fn sobs(&self) -> &[SOBS] {
+        &self.sobs
+    }
+    pub fn obj(&self) -> &[OBJ] {
+        &self.obj_
+    }
+    pub fn objs(&self) -> &[OBJS] {
+        &self.objs
+    }
+    pub fn stag(&self) -> &[STAG] {
+        &self.stag
+    }
+    pub fn stas(&self) -> &[STAS] {
+        &self.stas
+    }
+    pub fn sndt(&self) -> &[SNDT] {
+        &self.sndt
+    }
+    pub fn door(&self) -> &[DOOR] {
+        &self.door
+    }
+    pub fn objn(&self) -> &[OBJN] {
+        &self.objn
+    }
+    pub fn arcn(&self) -> &[ARCN] {
+        &self.arcn
+    }
+    pub fn unknown_chunks(&self) -> &[UnknownChunk] {
+        &self.unknown
+    }
+}
+
+#[allow(dead_code)]
+impl BZS {
+    pub fn file(&self) -> &[FILE] {
+        &self.file
+    }
+    pub fn stif(&self) -> &[STIF] {
+        &self.stif
+    }
+    pub fn rmpl(&self) -> &[RMPL] {
+        &self.rmpl
+    }
+    pub fn scen(&self) -> &[SCEN] {
+        &self.scen
+    }
+    pub fn cam(&self) -> &[CAM] {
+        &self.cam_
+    }
+    pub fn pcam(&self) -> &[PCAM] {
+        &self.pcam
+    }
+    pub fn path(&self) -> &[PATH] {
+        &self.path
+    }
+    pub fn spth(&self) -> &[SPTH] {
+        &self.spth
+    }
+    pub fn pnt(&self) -> &[PNT] {
+        &self.pnt_
+    }
+    pub fn spnt(&self) -> &[SPNT] {
+        &self.spnt
+    }
+    pub fn bpnt(&self) -> &[BPNT] {
+        &self.bpnt
+    }
+    pub fn area(&self) -> &[AREA] {
+        &self.area
+    }
+    pub fn evnt(&self) -> &[EVNT] {
+        &self.evnt
+    }
+    pub fn ply(&self) -> &[PLY] {
+        &self.ply_
+    }
+    pub fn lyse(&self) -> &[LYSE] {
+        &self.lyse
+    }
+    pub fn lylt(&self) -> &[LYLT] {
+        &self.lylt
+    }
+    pub fn sobj(&self) -> &[SOBJ] {
+        self.objects.sobj()
+    }
+    pub fn sobs(&self) -> &[SOBS] {
+        self.objects.sobs()
+    }
+    /// Objects placed outside of any layer
+    pub fn objects(&self) -> &LayerData {
+        &self.objects
+    }
+    pub fn layers(&self) -> &[LayerData; LAYER_COUNT] {
+        &self.layers
+    }
+    pub fn layer(&self, layer: usize) -> Option<&LayerData> {
+        self.layers.get(layer)
+    }
+    pub fn unknown_chunks(&self) -> &[UnknownChunk] {
+        &self.unknown
+    }
+}
+
+#[cfg(test)]
+impl BZS {
+    /// Reads a bzs made of the raw data of each chunk. Lets the tests of the viewer build a stage
+    pub fn from_chunks(chunks: &[(&[u8; 4], u16, Vec<u8>)]) -> Self {
+        let mut data = b"V001".to_vec();
+        data.extend_from_slice(&(chunks.len() as u16).to_be_bytes());
+        data.extend_from_slice(&NODE_PADDING.to_be_bytes());
+        data.extend_from_slice(&0xCu32.to_be_bytes());
+
+        // Offsets are relative to the start of each entry
+        let mut data_pos = 0xC + 0xC * chunks.len();
+        for (i, (magic, count, chunk)) in chunks.iter().enumerate() {
+            data.extend_from_slice(*magic);
+            data.extend_from_slice(&count.to_be_bytes());
+            data.extend_from_slice(&NODE_PADDING.to_be_bytes());
+            data.extend_from_slice(&((data_pos - 0xC * (i + 1)) as u32).to_be_bytes());
+            data_pos += chunk.len();
+        }
+        chunks
+            .iter()
+            .for_each(|(_, _, chunk)| data.extend_from_slice(chunk));
+
+        Self::read(&mut std::io::Cursor::new(data)).expect("Should be a valid BZS")
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, io::Cursor};
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let bzs = BZS::read(&mut Cursor::new(data)).expect("Should be a valid BZS");
+        let mut out = Vec::new();
+        bzs.write(&mut out).expect("Should write");
+        out
+    }
+
+    fn entry(magic: &[u8; 4], count: u16, offset: u32) -> Vec<u8> {
+        let mut data = magic.to_vec();
+        data.extend_from_slice(&count.to_be_bytes());
+        data.extend_from_slice(&NODE_PADDING.to_be_bytes());
+        data.extend_from_slice(&offset.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn round_trip_is_byte_identical() {
+        // Unknown chunk, OBJ outside of the layers and a single layer with names
+        let mut data = entry(b"V001", 3, 0xC);
+        data.extend(entry(b"ZZZZ", 1, 0x24));
+        data.extend(entry(b"OBJ ", 1, 0x1C));
+        data.extend(entry(b"LAY ", 29, 0x34));
+        data.extend_from_slice(&[1, 2, 3, 4]);
+
+        data.extend_from_slice(&0xFFFF_FFFFu32.to_be_bytes());
+        data.extend_from_slice(&0x0000_00FFu32.to_be_bytes());
+        for f in [1.5f32, -2.0, 300.0] {
+            data.extend_from_slice(&f.to_be_bytes());
+        }
+        data.extend_from_slice(&[0x40, 0x00, 0x00, 0x00, 0x80, 0x00, 0xFC, 0x01]);
+        // Garbage after the terminator must be kept
+        data.extend_from_slice(b"Tubo\0\xAB\xCD\xEF");
+
+        for i in 0..29u32 {
+            match i {
+                1 => data.extend(&entry(b"LAY ", 1, 0xE0)[4..]),
+                _ => data.extend(&entry(b"LAY ", 0, 0)[4..]),
+            }
+        }
+        data.extend(entry(b"OBJN", 2, 0xC));
+        data.extend_from_slice(b"\x00\x04\x00\x06A\0Bc\0\xFF\xFF\xFF");
+        data.resize(0x160, 0xFF);
+
+        let bzs = BZS::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(bzs.unknown_chunks()[0].data, [1, 2, 3, 4]);
+        assert_eq!(bzs.objects().obj()[0].name.as_str(), "Tubo");
+        assert_eq!(bzs.layers()[1].objn()[1].name, "Bc");
+        assert_eq!(round_trip(&data), data);
+    }
+
+    #[test]
+    fn round_trip_sample_file() {
+        // A small room: an event, a spawn, an object outside of the layers and objects in layers 0 and 1
+        let data = include_bytes!("../../test_data/sample.bzs");
+        let bzs = BZS::read(&mut Cursor::new(data)).expect("Should be a valid BZS");
+        assert_eq!(bzs.scen()[0].name.as_str(), "F000");
+        assert_eq!(bzs.evnt()[0].name.as_str(), "ZeldaLeavesRoom");
+        assert_eq!(bzs.lyse()[0].layer, 1);
+        assert_eq!(bzs.objects().obj()[0].name.as_str(), "Tubo");
+        assert_eq!(bzs.layers()[0].objn()[0].name, "Tubo");
+        assert_eq!(bzs.layers()[1].door()[0].angle, [0, -0x8000, 0]);
+        assert_eq!(bzs.layers()[1].arcn()[0].name, "DoorA00");
+        assert_eq!(round_trip(data), data);
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let mut bzs = BZS::default();
+        bzs.scen.push(SCEN {
+            name: SizedString::new("D100"),
+            room: 1,
+            layer: 2,
+            entrance: 3,
+            night: 0,
+            byte5: 0,
+            flag6: 0,
+            zero: 0,
+            save_prompt: 1,
+        });
+        bzs.rmpl.push(RMPL {
+            id: 2,
+            data: vec![RMPLData { data: 0 }, RMPLData { data: 1 }],
+        });
+        bzs.layers[4].door.push(DOOR {
+            params1: 0x1234_5678,
+            params2: 0xFFFF_FFFF,
+            pos: [1.0, 2.0, 3.0],
+            angle: [0, -0x4000, 0],
+            id: 0xFC02,
+            name: SizedString::new("DoorA"),
+        });
+        bzs.layers[4].arcn.push(ARCN {
+            name: "DoorA".to_string(),
+        });
+
+        let mut data = Vec::new();
+        bzs.write(&mut data).unwrap();
+        assert_eq!(data.len() % FILE_ALIGNMENT, 0);
+
+        let read = BZS::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(read.scen()[0].name.as_str(), "D100");
+        assert_eq!(read.scen()[0].save_prompt, 1);
+        assert_eq!(read.rmpl()[0].id, 2);
+        assert_eq!(read.rmpl()[0].data[1].data, 1);
+        assert_eq!(read.layers()[4].door()[0].angle, [0, -0x4000, 0]);
+        assert_eq!(read.layers()[4].arcn()[0].name, "DoorA");
+        assert!(read.layers()[3].door().is_empty());
+
+        assert_eq!(round_trip(&data), data);
+    }
+
+    #[test]
+    #[ignore = "needs the preprocessed game files in `Collision Files`"]
+    fn round_trip_real_files() {
+        let files = glob::glob("Collision Files/**/*.bzs").expect("Invalid Glob pattern");
+        let mut count = 0;
+        for path in files.flatten() {
+            let data = fs::read(&path).unwrap();
+            assert_eq!(round_trip(&data), data, "{}", path.display());
+            count += 1;
+        }
+        assert!(count > 0, "No BZS files found in `Collision Files`");
+    }
+}
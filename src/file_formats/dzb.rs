@@ -1,5 +1,5 @@
-use byteorder::{ReadBytesExt, BE};
-use std::io::{Read, Seek, SeekFrom};
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
 
 use glam::Vec3;
 
@@ -45,6 +45,7 @@ pub struct Block {
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct Group {
+    pub name: String,
     pub name_offset: u32,
     pub scale: glam::Vec3,
     pub rotation: [i16; 3],
@@ -56,7 +57,7 @@ pub struct Group {
     pub room_id: u16,
     pub first_vtx_idx: u16,
     pub tree_index: u16,
-    pub info: u16,
+    pub info: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -176,6 +177,7 @@ impl DZB {
             .expect("Should be in bounds");
         for _ in 0..header.groups_count {
             groups.push(Group {
+                name: String::new(),
                 name_offset: reader.read_u32::<BE>()?,
                 scale: Vec3::new(
                     reader.read_f32::<BE>()?,
@@ -199,10 +201,26 @@ impl DZB {
                 room_id: reader.read_u16::<BE>()?,
                 first_vtx_idx: reader.read_u16::<BE>()?,
                 tree_index: reader.read_u16::<BE>()?,
-                info: reader.read_u16::<BE>()?,
+                info: reader.read_u32::<BE>()?,
             });
         }
 
+        // Group Names
+        for group in groups.iter_mut() {
+            reader
+                .seek(SeekFrom::Start(group.name_offset as _))
+                .expect("Should be in bounds");
+            let mut name = Vec::new();
+            loop {
+                let c = reader.read_u8()?;
+                if c == 0 {
+                    break;
+                }
+                name.push(c);
+            }
+            group.name = String::from_utf8_lossy(&name).into_owned();
+        }
+
         // Properties
         reader
             .seek(SeekFrom::Start(header.properties_offset as _))
@@ -230,3 +248,416 @@ impl DZB {
         })
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                                 DZB Encoding                                                      //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+const DZB_HEADER_SIZE: u32 = 0x34;
+const VERTEX_SIZE: u32 = 0x0C;
+const TRIANGLE_SIZE: u32 = 0x0A;
+const BLOCK_SIZE: u32 = 0x02;
+const TREE_NODE_SIZE: u32 = 0x14;
+const GROUP_SIZE: u32 = 0x34;
+const PROPERTY_SIZE: u32 = 0x10;
+
+// Used for missing parents/children in tree nodes and groups without triangles
+const NO_INDEX: u16 = 0xFFFF;
+
+// Tree nodes with this flag are leaves and the first branch is the index of their block
+const TREE_NODE_LEAF: u16 = 0x0001;
+
+// A node is split into 8 while it has more triangles than this and is not too deep
+const MAX_BLOCK_TRIANGLES: usize = 16;
+const MAX_TREE_DEPTH: u32 = 8;
+
+fn align4(value: u32) -> u32 {
+    (value + 3) & !3
+}
+
+// Everything the tree is built into. Triangles are appended in block order
+struct TreeOutput {
+    tris: Vec<Triangle>,
+    blocks: Vec<Block>,
+    tree_nodes: Vec<OctreeNode>,
+}
+
+#[allow(dead_code)]
+impl DZB {
+    // Builds the (sub)tree for `tris` inside the cube at `center`. Returns the index of the created node
+    fn build_tree(
+        &self,
+        tris: Vec<Triangle>,
+        center: Vec3,
+        half_size: f32,
+        parent_node_idx: u16,
+        depth: u32,
+        out: &mut TreeOutput,
+    ) -> usize {
+        let node_idx = out.tree_nodes.len();
+        out.tree_nodes.push(OctreeNode {
+            flags: 0,
+            parent_node_idx,
+            branches: [NO_INDEX; 8],
+        });
+
+        if tris.len() <= MAX_BLOCK_TRIANGLES || depth >= MAX_TREE_DEPTH {
+            let node = &mut out.tree_nodes[node_idx];
+            node.flags = TREE_NODE_LEAF;
+            node.branches[0] = out.blocks.len() as u16;
+            out.blocks.push(Block {
+                starting_tri_idx: out.tris.len() as u16,
+            });
+            out.tris.extend(tris);
+            return node_idx;
+        }
+
+        // Each triangle belongs to the octant its center is in. The game computes the bounds of the
+        //  nodes from the triangles, so they do not need to fit the cubes exactly
+        let mut octants: [Vec<Triangle>; 8] = Default::default();
+        for tri in tris {
+            let tri_center = tri
+                .vert_idx
+                .iter()
+                .map(|&idx| self.verts[idx as usize])
+                .sum::<Vec3>()
+                / 3.0;
+            let octant = (tri_center.x >= center.x) as usize
+                | ((tri_center.y >= center.y) as usize) << 1
+                | ((tri_center.z >= center.z) as usize) << 2;
+            octants[octant].push(tri);
+        }
+
+        for (i, octant) in octants.into_iter().enumerate() {
+            if octant.is_empty() {
+                continue;
+            }
+            let direction = Vec3::new(
+                if i & 1 != 0 { 1.0 } else { -1.0 },
+                if i & 2 != 0 { 1.0 } else { -1.0 },
+                if i & 4 != 0 { 1.0 } else { -1.0 },
+            );
+            let child_idx = self.build_tree(
+                octant,
+                center + direction * (half_size / 2.0),
+                half_size / 2.0,
+                node_idx as u16,
+                depth + 1,
+                out,
+            );
+            out.tree_nodes[node_idx].branches[i] = child_idx as u16;
+        }
+
+        node_idx
+    }
+
+    /// Rebuilds the triangle order, blocks, tree nodes and the vertex/tree indices of the groups from the
+    ///  vertex, triangle and group lists. Name offsets are assigned when writing.
+    pub fn regenerate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(tri) = self
+            .tris
+            .iter()
+            .find(|tri| tri.group_idx as usize >= self.groups.len())
+        {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Triangle references missing group {}", tri.group_idx),
+            )));
+        }
+        if let Some(tri) = self.tris.iter().find(|tri| {
+            tri.vert_idx
+                .iter()
+                .any(|&idx| idx as usize >= self.verts.len())
+        }) {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Triangle references missing vertex {:?}", tri.vert_idx),
+            )));
+        }
+
+        // Triangles are sorted by group, and within a group by block
+        let mut out = TreeOutput {
+            tris: Vec::with_capacity(self.tris.len()),
+            blocks: Vec::new(),
+            tree_nodes: Vec::new(),
+        };
+        let mut group_indices = Vec::with_capacity(self.groups.len());
+        for group_idx in 0..self.groups.len() {
+            let group_tris: Vec<Triangle> = self
+                .tris
+                .iter()
+                .filter(|tri| tri.group_idx as usize == group_idx)
+                .cloned()
+                .collect();
+
+            let first_vtx_idx = group_tris
+                .iter()
+                .flat_map(|tri| tri.vert_idx)
+                .min()
+                .unwrap_or(0);
+
+            if group_tris.is_empty() {
+                group_indices.push((first_vtx_idx, NO_INDEX));
+                continue;
+            }
+
+            // The root node is a cube around all the vertices of the group
+            let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+            for tri in &group_tris {
+                for &idx in &tri.vert_idx {
+                    min = min.min(self.verts[idx as usize]);
+                    max = max.max(self.verts[idx as usize]);
+                }
+            }
+            let half_size = (max - min).max_element() / 2.0;
+            let tree_index = self.build_tree(
+                group_tris,
+                (min + max) / 2.0,
+                half_size,
+                NO_INDEX,
+                0,
+                &mut out,
+            );
+            group_indices.push((first_vtx_idx, tree_index as u16));
+        }
+
+        if out.tris.len() > u16::MAX as usize || out.tree_nodes.len() >= NO_INDEX as usize {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                "Too many triangles for a DZB",
+            )));
+        }
+
+        for (group, (first_vtx_idx, tree_index)) in self.groups.iter_mut().zip(group_indices) {
+            group.first_vtx_idx = first_vtx_idx;
+            group.tree_index = tree_index;
+        }
+        self.tris = out.tris;
+        self.blocks = out.blocks;
+        self.tree_nodes = out.tree_nodes;
+
+        Ok(())
+    }
+
+    /// Writes the DZB after regenerating the blocks, tree nodes and group table
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+        let mut dzb = self.clone();
+        dzb.regenerate()?;
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                 Layout Sections                                       //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        let vert_offset = DZB_HEADER_SIZE;
+        let triangle_offset = vert_offset + dzb.verts.len() as u32 * VERTEX_SIZE;
+        let blocks_offset = align4(triangle_offset + dzb.tris.len() as u32 * TRIANGLE_SIZE);
+        let tree_nodes_offset = align4(blocks_offset + dzb.blocks.len() as u32 * BLOCK_SIZE);
+        let groups_offset = tree_nodes_offset + dzb.tree_nodes.len() as u32 * TREE_NODE_SIZE;
+        let properties_offset = groups_offset + dzb.groups.len() as u32 * GROUP_SIZE;
+        let names_offset = properties_offset + dzb.properties.len() as u32 * PROPERTY_SIZE;
+
+        // Group names are null terminated strings after all other data
+        let mut names = Vec::new();
+        for group in dzb.groups.iter_mut() {
+            group.name_offset = names_offset + names.len() as u32;
+            names.extend_from_slice(group.name.as_bytes());
+            names.push(0);
+        }
+        let end = names_offset + names.len() as u32;
+        names.resize(names.len() + (align4(end) - end) as usize, 0);
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                    Write File                                         //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        for (count, offset) in [
+            (dzb.verts.len(), vert_offset),
+            (dzb.tris.len(), triangle_offset),
+            (dzb.blocks.len(), blocks_offset),
+            (dzb.tree_nodes.len(), tree_nodes_offset),
+            (dzb.groups.len(), groups_offset),
+            (dzb.properties.len(), properties_offset),
+        ] {
+            writer.write_u32::<BE>(count as u32)?;
+            writer.write_u32::<BE>(offset)?;
+        }
+        writer.write_u32::<BE>(0)?;
+
+        // Vertices
+        for v in dzb.verts.iter().flat_map(|v| v.to_array()) {
+            writer.write_f32::<BE>(v)?;
+        }
+
+        // Triangles
+        for tri in &dzb.tris {
+            for idx in tri.vert_idx {
+                writer.write_u16::<BE>(idx)?;
+            }
+            writer.write_u16::<BE>(tri.prop_idx)?;
+            writer.write_u16::<BE>(tri.group_idx)?;
+        }
+        let end = triangle_offset + dzb.tris.len() as u32 * TRIANGLE_SIZE;
+        writer.write_all(&vec![0; (blocks_offset - end) as usize])?;
+
+        // Blocks
+        for block in &dzb.blocks {
+            writer.write_u16::<BE>(block.starting_tri_idx)?;
+        }
+        let end = blocks_offset + dzb.blocks.len() as u32 * BLOCK_SIZE;
+        writer.write_all(&vec![0; (tree_nodes_offset - end) as usize])?;
+
+        // Tree Nodes
+        for node in &dzb.tree_nodes {
+            writer.write_u16::<BE>(node.flags)?;
+            writer.write_u16::<BE>(node.parent_node_idx)?;
+            for branch in node.branches {
+                writer.write_u16::<BE>(branch)?;
+            }
+        }
+
+        // Groups
+        for group in &dzb.groups {
+            writer.write_u32::<BE>(group.name_offset)?;
+            for v in group.scale.to_array() {
+                writer.write_f32::<BE>(v)?;
+            }
+            for r in group.rotation {
+                writer.write_i16::<BE>(r)?;
+            }
+            writer.write_u16::<BE>(group.unk1)?;
+            for v in group.translation.to_array() {
+                writer.write_f32::<BE>(v)?;
+            }
+            writer.write_u16::<BE>(group.parent_group_idx)?;
+            writer.write_u16::<BE>(group.next_sibling_group)?;
+            writer.write_u16::<BE>(group.first_child_group_index)?;
+            writer.write_u16::<BE>(group.room_id)?;
+            writer.write_u16::<BE>(group.first_vtx_idx)?;
+            writer.write_u16::<BE>(group.tree_index)?;
+            writer.write_u32::<BE>(group.info)?;
+        }
+
+        // Properties
+        for property in &dzb.properties {
+            writer.write_u32::<BE>(property.info1)?;
+            writer.write_u32::<BE>(property.info2)?;
+            writer.write_u32::<BE>(property.info3)?;
+            writer.write_u32::<BE>(property.pass_flag)?;
+        }
+
+        // Group Names
+        writer.write_all(&names)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn group(name: &str, parent_group_idx: u16, first_child_group_index: u16) -> Group {
+        Group {
+            name: name.to_string(),
+            name_offset: 0,
+            scale: Vec3::ONE,
+            rotation: [0, 0x4000, 0],
+            unk1: 0,
+            translation: Vec3::new(10.0, 20.0, 30.0),
+            parent_group_idx,
+            next_sibling_group: NO_INDEX,
+            first_child_group_index,
+            room_id: 3,
+            first_vtx_idx: 0,
+            tree_index: 0,
+            info: 0,
+        }
+    }
+
+    // Collects the triangles reachable from a tree node through its blocks
+    fn tree_triangles(dzb: &DZB, node_idx: u16, out: &mut Vec<usize>) {
+        let node = &dzb.tree_nodes[node_idx as usize];
+        if node.flags & TREE_NODE_LEAF != 0 {
+            let block = node.branches[0] as usize;
+            let start = dzb.blocks[block].starting_tri_idx as usize;
+            let end = dzb
+                .blocks
+                .get(block + 1)
+                .map_or(dzb.tris.len(), |b| b.starting_tri_idx as usize);
+            out.extend(start..end);
+        } else {
+            for &branch in node.branches.iter().filter(|&&b| b != NO_INDEX) {
+                assert_eq!(dzb.tree_nodes[branch as usize].parent_node_idx, node_idx);
+                tree_triangles(dzb, branch, out);
+            }
+        }
+    }
+
+    #[test]
+    fn write_round_trip() {
+        // A grid split between a root group and its child, with an empty group at the end
+        let mut verts = Vec::new();
+        for x in 0..20 {
+            for z in 0..20 {
+                verts.push(Vec3::new(
+                    x as f32 * 100.0,
+                    (x * z) as f32,
+                    z as f32 * 100.0,
+                ));
+            }
+        }
+        let mut tris = Vec::new();
+        for x in 0..19u16 {
+            for z in 0..19u16 {
+                let i = x * 20 + z;
+                tris.push(Triangle {
+                    vert_idx: [i, i + 1, i + 20],
+                    prop_idx: x,
+                    group_idx: (x >= 10) as u16,
+                });
+            }
+        }
+
+        let dzb = DZB {
+            verts,
+            tris,
+            blocks: Vec::new(),
+            tree_nodes: Vec::new(),
+            groups: vec![
+                group("root", NO_INDEX, 1),
+                group("child", 0, NO_INDEX),
+                group("empty", 0, NO_INDEX),
+            ],
+            properties: Vec::new(),
+        };
+
+        let mut data = Vec::new();
+        dzb.write_to(&mut data).expect("Should write");
+        let read = DZB::from_file(&mut Cursor::new(&data)).expect("Should be a valid DZB");
+
+        assert_eq!(read.verts, dzb.verts);
+        assert_eq!(read.tris.len(), dzb.tris.len());
+        let names: Vec<&str> = read.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["root", "child", "empty"]);
+        assert_eq!(read.groups[2].tree_index, NO_INDEX);
+
+        // Every triangle is reachable from the tree of its own group
+        let mut seen = Vec::new();
+        for (group_idx, group) in read.groups.iter().enumerate().take(2) {
+            let mut group_tris = Vec::new();
+            tree_triangles(&read, group.tree_index, &mut group_tris);
+            assert!(group_tris
+                .iter()
+                .all(|&i| read.tris[i].group_idx as usize == group_idx));
+            seen.extend(group_tris);
+        }
+        seen.sort();
+        assert_eq!(seen, (0..read.tris.len()).collect::<Vec<_>>());
+
+        // Writing again gives the same file
+        let mut rewritten = Vec::new();
+        read.write_to(&mut rewritten).expect("Should write");
+        assert_eq!(rewritten, data);
+    }
+}
@@ -1,7 +1,8 @@
-use byteorder::{ReadBytesExt, BE};
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
 use std::{
     cmp::max,
-    io::{Read, Seek, SeekFrom},
+    collections::HashMap,
+    io::{Error, ErrorKind, Read, Seek, SeekFrom, Write},
 };
 
 use glam::Vec3;
@@ -21,7 +22,7 @@ struct Header {
     area_xy_blocks_shift: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Octree {
     Leaf(Vec<u16>),
     Branch(Vec<Octree>),
@@ -78,7 +79,7 @@ impl Octree {
                         max = val;
                     }
                 }
-                max as usize
+                max
             }
         }
     }
@@ -127,6 +128,10 @@ pub struct KCL {
     pub octree: Octree,
     pub prism_thickness: f32,
     pub area_min_pos: Vec3,
+    pub area_width_mask: [u32; 3],
+    pub block_width_shift: u32,
+    pub area_x_blocks_shift: u32,
+    pub area_xy_blocks_shift: u32,
 }
 
 impl KCL {
@@ -159,9 +164,11 @@ impl KCL {
 
         // Parse Octree
         let shift = header.block_width_shift;
-        let num_initial = ((!header.area_z_width_mask >> shift) << header.area_xy_blocks_shift)
+        // This gives the index of the last block, so add one for the count
+        let num_initial = (((!header.area_z_width_mask >> shift) << header.area_xy_blocks_shift)
             | ((!header.area_y_width_mask >> shift) << header.area_x_blocks_shift)
-            | (!header.area_x_width_mask >> shift);
+            | (!header.area_x_width_mask >> shift))
+            + 1;
 
         let octree: Octree = Octree::new(header.block_data_offset, reader, num_initial);
 
@@ -243,9 +250,17 @@ impl KCL {
             vtx: position_vecs,
             nrm: normal_vecs,
             prism: raw_prisms,
-            octree: octree,
+            octree,
             prism_thickness: header.prism_thickness,
             area_min_pos: header.area_min_pos,
+            area_width_mask: [
+                header.area_x_width_mask,
+                header.area_y_width_mask,
+                header.area_z_width_mask,
+            ],
+            block_width_shift: header.block_width_shift,
+            area_x_blocks_shift: header.area_x_blocks_shift,
+            area_xy_blocks_shift: header.area_xy_blocks_shift,
         })
     }
 
@@ -275,3 +290,515 @@ impl KCL {
         tris
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                                 KCL Encoding                                                      //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+const KCL_HEADER_SIZE: u32 = 0x38;
+const PRISM_SIZE: u32 = 0x10;
+
+// Default depth of the collision prism below each triangle
+const DEFAULT_PRISM_THICKNESS: f32 = 300.0;
+
+// Padding added around the bounds of the triangles for the spatial index
+const AREA_PADDING: f32 = 25.0;
+
+// A cube is split into 8 when it holds more triangles than this and is larger than the minimum width
+const MAX_CUBE_TRIANGLES: usize = 32;
+const MIN_CUBE_WIDTH_SHIFT: u32 = 8;
+
+// Triangles within this distance of a cube are considered part of it
+const CUBE_MARGIN: f32 = 1.0;
+
+impl Prism {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+        writer.write_f32::<BE>(self.height)?;
+        writer.write_u16::<BE>(self.pos_i)?;
+        writer.write_u16::<BE>(self.fnrm_i)?;
+        for enrm in self.enrm_i {
+            writer.write_u16::<BE>(enrm)?;
+        }
+        writer.write_u16::<BE>(self.attribute)?;
+        Ok(())
+    }
+}
+
+// Separating axis test between a triangle and an axis aligned box
+fn triangle_overlaps_box(tri: &[Vec3; 3], center: Vec3, half_size: Vec3) -> bool {
+    let v = tri.map(|p| p - center);
+    let edges = [v[1] - v[0], v[2] - v[1], v[0] - v[2]];
+
+    let separated = |axis: Vec3| {
+        if axis.length_squared() < 1e-12 {
+            return false;
+        }
+        let p = v.map(|p| axis.dot(p));
+        let r = half_size.dot(axis.abs());
+        p[0].min(p[1]).min(p[2]) > r || p[0].max(p[1]).max(p[2]) < -r
+    };
+
+    // Box face normals, triangle normal and the cross products of the edges with the box axes
+    let box_axes = [Vec3::X, Vec3::Y, Vec3::Z];
+    if box_axes.iter().any(|&axis| separated(axis)) || separated(edges[0].cross(edges[1])) {
+        return false;
+    }
+    !edges
+        .iter()
+        .any(|&edge| box_axes.iter().any(|&axis| separated(axis.cross(edge))))
+}
+
+// Dedups vectors by their exact bit patterns and returns the index of `vec`
+fn insert_vec(vecs: &mut Vec<Vec3>, lookup: &mut HashMap<[u32; 3], u16>, vec: Vec3) -> Option<u16> {
+    let key = vec.to_array().map(f32::to_bits);
+    if let Some(&idx) = lookup.get(&key) {
+        return Some(idx);
+    }
+    let idx = u16::try_from(vecs.len()).ok()?;
+    vecs.push(vec);
+    lookup.insert(key, idx);
+    Some(idx)
+}
+
+impl Octree {
+    // Builds the cube at `min` with a width of (1 << shift) out of the triangles in `candidates`
+    fn build(tris: &[[Vec3; 3]], candidates: &[u16], min: Vec3, shift: u32) -> Self {
+        let width = (1u32 << shift) as f32;
+        let half_size = Vec3::splat(width / 2.0);
+        let center = min + half_size;
+
+        // Prism indices are 1 based
+        let inside: Vec<u16> = candidates
+            .iter()
+            .copied()
+            .filter(|&i| {
+                triangle_overlaps_box(
+                    &tris[i as usize - 1],
+                    center,
+                    half_size + Vec3::splat(CUBE_MARGIN),
+                )
+            })
+            .collect();
+
+        if inside.len() <= MAX_CUBE_TRIANGLES || shift <= MIN_CUBE_WIDTH_SHIFT {
+            return Self::Leaf(inside);
+        }
+
+        // Children are ordered with x as the lowest bit, then y and z
+        let child_width = (1u32 << (shift - 1)) as f32;
+        Self::Branch(
+            (0..8)
+                .map(|i| {
+                    let offset = Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32);
+                    Self::build(tris, &inside, min + offset * child_width, shift - 1)
+                })
+                .collect(),
+        )
+    }
+
+    // Serializes the children of a branch into `nodes` at `base`. Leaf lists are collected into `lists`
+    //  and patched once the size of all nodes is known
+    fn write_branch(
+        children: &[Octree],
+        base: usize,
+        nodes: &mut Vec<u32>,
+        leaves: &mut Vec<(usize, usize, Vec<u16>)>,
+    ) {
+        let mut branches = Vec::new();
+        for (i, child) in children.iter().enumerate() {
+            match child {
+                Octree::Leaf(indices) => leaves.push((base / 4 + i, base, indices.clone())),
+                Octree::Branch(grand_children) => {
+                    let child_base = nodes.len() * 4;
+                    nodes[base / 4 + i] = (child_base - base) as u32;
+                    nodes.resize(nodes.len() + grand_children.len(), 0);
+                    branches.push((child_base, grand_children));
+                }
+            }
+        }
+        for (child_base, grand_children) in branches {
+            Self::write_branch(grand_children, child_base, nodes, leaves);
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl KCL {
+    /// Builds the prisms and spatial index from a list of triangles.
+    /// Degenerate triangles can not be stored as a prism. They are an error rather than being skipped,
+    ///  since that would shift the indices of the later prisms against their PLC entries.
+    pub fn from_triangles(tris: &[KCLTriangle]) -> Result<Self, Box<dyn std::error::Error>> {
+        let too_large = || {
+            Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                "Too many unique vectors for a KCL",
+            ))
+        };
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                   Build Prisms                                        //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        let mut vtx = Vec::new();
+        let mut nrm = Vec::new();
+        let mut vtx_lookup = HashMap::new();
+        let mut nrm_lookup = HashMap::new();
+        let mut prism = Vec::with_capacity(tris.len());
+        let mut prism_tris = Vec::with_capacity(tris.len());
+
+        for (i, tri) in tris.iter().enumerate() {
+            let [a, b, c] = tri.vertices;
+
+            // Edge normals face outwards from the triangle
+            let fnrm = (b - a).cross(c - a).normalize();
+            let enrm = [
+                fnrm.cross(c - a).normalize(),
+                (b - a).cross(fnrm).normalize(),
+                fnrm.cross(b - c).normalize(),
+            ];
+            let height = (b - a).dot(enrm[2]);
+
+            if !fnrm.is_finite()
+                || enrm.iter().any(|n| !n.is_finite())
+                || height.is_nan()
+                || height <= 0.0
+            {
+                return Err(Box::new(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Triangle {i} is degenerate and can not be stored as a prism"),
+                )));
+            }
+
+            prism.push(Prism {
+                height,
+                pos_i: insert_vec(&mut vtx, &mut vtx_lookup, a).ok_or_else(too_large)?,
+                fnrm_i: insert_vec(&mut nrm, &mut nrm_lookup, fnrm).ok_or_else(too_large)?,
+                enrm_i: [
+                    insert_vec(&mut nrm, &mut nrm_lookup, enrm[0]).ok_or_else(too_large)?,
+                    insert_vec(&mut nrm, &mut nrm_lookup, enrm[1]).ok_or_else(too_large)?,
+                    insert_vec(&mut nrm, &mut nrm_lookup, enrm[2]).ok_or_else(too_large)?,
+                ],
+                attribute: tri.attribute,
+            });
+            prism_tris.push(tri.vertices);
+        }
+
+        if prism.len() >= u16::MAX as usize {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                "Too many triangles for a KCL",
+            )));
+        }
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                 Build Spatial Index                                   //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+        for v in prism_tris.iter().flatten() {
+            min = min.min(*v);
+            max = max.max(*v);
+        }
+        if prism_tris.is_empty() {
+            (min, max) = (Vec3::ZERO, Vec3::ZERO);
+        }
+        let area_min_pos = (min - Vec3::splat(AREA_PADDING)).floor();
+        let area_max_pos = max + Vec3::splat(AREA_PADDING);
+
+        // Each axis covers the smallest power of two that fits the triangles
+        let width_shift = (area_max_pos - area_min_pos)
+            .to_array()
+            .map(|width| (width.max(1.0).log2().ceil() as u32).clamp(MIN_CUBE_WIDTH_SHIFT, 30));
+        let area_width_mask = width_shift.map(|shift| u32::MAX << shift);
+
+        // The first level of blocks is one step smaller than the smallest axis
+        let block_width_shift = (width_shift.iter().min().unwrap() - 1).max(MIN_CUBE_WIDTH_SHIFT);
+        let blocks = width_shift.map(|shift| shift - block_width_shift);
+        let area_x_blocks_shift = blocks[0];
+        let area_xy_blocks_shift = blocks[0] + blocks[1];
+
+        // Each triangle is only tested against the root blocks its bounds touch
+        let block_width = (1u32 << block_width_shift) as f32;
+        let block_counts = blocks.map(|shift| 1usize << shift);
+        let block_index = |x: usize, y: usize, z: usize| {
+            (z << area_xy_blocks_shift) | (y << area_x_blocks_shift) | x
+        };
+        let mut bins = vec![Vec::new(); block_counts.iter().product()];
+        for (i, tri) in prism_tris.iter().enumerate() {
+            let (tri_min, tri_max) = tri.iter().fold(
+                (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(lo, hi), v| (lo.min(*v), hi.max(*v)),
+            );
+            // Blocks are closed at both ends, so one touching a triangle at its far edge still counts
+            let lo = (tri_min - Vec3::splat(CUBE_MARGIN) - area_min_pos) / block_width;
+            let hi = (tri_max + Vec3::splat(CUBE_MARGIN) - area_min_pos) / block_width;
+            let range = |axis: usize| {
+                let last = block_counts[axis] - 1;
+                let first = (lo[axis].ceil() - 1.0).max(0.0) as usize;
+                first.min(last)..=(hi[axis].floor().max(0.0) as usize).min(last)
+            };
+            for z in range(2) {
+                for y in range(1) {
+                    for x in range(0) {
+                        // Prism indices are 1 based
+                        bins[block_index(x, y, z)].push(i as u16 + 1);
+                    }
+                }
+            }
+        }
+
+        let mut root = Vec::with_capacity(bins.len());
+        for z in 0..block_counts[2] {
+            for y in 0..block_counts[1] {
+                for x in 0..block_counts[0] {
+                    let offset = Vec3::new(x as f32, y as f32, z as f32) * block_width;
+                    root.push(Octree::build(
+                        &prism_tris,
+                        &bins[block_index(x, y, z)],
+                        area_min_pos + offset,
+                        block_width_shift,
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            vtx,
+            nrm,
+            prism,
+            octree: Octree::Branch(root),
+            prism_thickness: DEFAULT_PRISM_THICKNESS,
+            area_min_pos,
+            area_width_mask,
+            block_width_shift,
+            area_x_blocks_shift,
+            area_xy_blocks_shift,
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+        let Octree::Branch(root) = &self.octree else {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                "The root of the KCL octree must be a branch",
+            )));
+        };
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                 Layout Sections                                       //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        let pos_dat_offset = KCL_HEADER_SIZE;
+        let nrm_data_offset = pos_dat_offset + self.vtx.len() as u32 * 0xC;
+        let prisms_start = nrm_data_offset + self.nrm.len() as u32 * 0xC;
+        let block_data_offset = prisms_start + self.prism.len() as u32 * PRISM_SIZE;
+
+        // Prisms are 1 indexed so the offset points one before the first prism
+        let prism_data_offset = prisms_start - PRISM_SIZE;
+
+        // Blocks are offsets relative to the node they are in. Branches point to the next node
+        //  while leaves point to the u16 before their triangle list (with the top bit set)
+        let mut nodes = vec![0u32; root.len()];
+        let mut leaves = Vec::new();
+        Octree::write_branch(root, 0, &mut nodes, &mut leaves);
+
+        // Lists start with a terminator so that empty lists have something to point to
+        let lists_start = nodes.len() * 4;
+        let mut lists: Vec<u16> = vec![0];
+        let mut list_offsets: HashMap<Vec<u16>, usize> = HashMap::new();
+        for (entry, base, indices) in leaves {
+            let list_offset = if indices.is_empty() {
+                lists_start
+            } else {
+                *list_offsets.entry(indices.clone()).or_insert_with(|| {
+                    let offset = lists_start + lists.len() * 2;
+                    lists.extend(indices);
+                    lists.push(0);
+                    offset
+                })
+            };
+            nodes[entry] = 0x8000_0000 | (list_offset - 2 - base) as u32;
+        }
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                    Write File                                         //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        writer.write_u32::<BE>(pos_dat_offset)?;
+        writer.write_u32::<BE>(nrm_data_offset)?;
+        writer.write_u32::<BE>(prism_data_offset)?;
+        writer.write_u32::<BE>(block_data_offset)?;
+        writer.write_f32::<BE>(self.prism_thickness)?;
+        for v in self.area_min_pos.to_array() {
+            writer.write_f32::<BE>(v)?;
+        }
+        for mask in self.area_width_mask {
+            writer.write_u32::<BE>(mask)?;
+        }
+        writer.write_u32::<BE>(self.block_width_shift)?;
+        writer.write_u32::<BE>(self.area_x_blocks_shift)?;
+        writer.write_u32::<BE>(self.area_xy_blocks_shift)?;
+
+        for v in self.vtx.iter().chain(&self.nrm).flat_map(|v| v.to_array()) {
+            writer.write_f32::<BE>(v)?;
+        }
+        for prism in &self.prism {
+            prism.write_to(writer)?;
+        }
+        for node in nodes {
+            writer.write_u32::<BE>(node)?;
+        }
+        for idx in lists {
+            writer.write_u16::<BE>(idx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Two root blocks along X, each a leaf with one prism. The last one used to be skipped
+    fn two_block_kcl() -> Vec<u8> {
+        let mut data = Vec::new();
+        let header: [u32; 4] = [0x38, 0x44, 0x74, 0xA4];
+        header
+            .iter()
+            .for_each(|v| data.extend_from_slice(&v.to_be_bytes()));
+        for v in [10.0f32, 0.0, 0.0, 0.0] {
+            data.extend_from_slice(&v.to_be_bytes()); // Thickness and min position
+        }
+        for v in [0xFFFF_F800u32, 0xFFFF_FC00, 0xFFFF_FC00, 10, 1, 1] {
+            data.extend_from_slice(&v.to_be_bytes()); // Masks and shifts
+        }
+        // One position and four normals
+        for v in [
+            0.0f32, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, -1.0, 0.0, 0.0,
+        ] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        // Prisms are 1 indexed, the first one is never read
+        data.resize(0x84, 0);
+        for _ in 0..2 {
+            data.extend_from_slice(&10.0f32.to_be_bytes());
+            for v in [0u16, 0, 1, 2, 3, 0] {
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        // Root blocks, then the prism lists. Lists start after the offset they are given at
+        for v in [0x8000_0008u32, 0x8000_000C] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        for v in [0u16, 1, 0, 2, 0] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn reads_every_root_block() {
+        let kcl = KCL::from_file(&mut Cursor::new(two_block_kcl())).expect("Should be a valid KCL");
+        match &kcl.octree {
+            Octree::Branch(blocks) => assert_eq!(blocks.len(), 2),
+            Octree::Leaf(_) => panic!("The root should be a branch"),
+        }
+        assert_eq!(kcl.prism.len(), 2);
+    }
+
+    // A bumpy grid so the octree needs to subdivide
+    fn bumpy_grid() -> Vec<KCLTriangle> {
+        let height =
+            |x: u16, z: u16| (x as f32 * 0.3).sin() * 400.0 + (z as f32 * 0.2).cos() * 300.0;
+        let pos =
+            |x: u16, z: u16| Vec3::new(x as f32 * 200.0 - 3000.0, height(x, z), z as f32 * 150.0);
+
+        let mut tris = Vec::new();
+        for x in 0..40 {
+            for z in 0..40 {
+                let (a, b, c, d) = (pos(x, z), pos(x + 1, z), pos(x, z + 1), pos(x + 1, z + 1));
+                tris.push(KCLTriangle {
+                    vertices: [a, c, b],
+                    face_normal: Vec3::Y,
+                    attribute: x % 7,
+                });
+                tris.push(KCLTriangle {
+                    vertices: [b, c, d],
+                    face_normal: Vec3::Y,
+                    attribute: z % 5,
+                });
+            }
+        }
+        tris
+    }
+
+    #[test]
+    fn from_triangles_round_trip() {
+        let tris = bumpy_grid();
+        let mut data = Vec::new();
+        KCL::from_triangles(&tris)
+            .expect("Should encode")
+            .write_to(&mut data)
+            .expect("Should write");
+
+        let decoded = KCL::from_file(&mut Cursor::new(&data))
+            .expect("Should be a valid KCL")
+            .get_triangles();
+
+        assert_eq!(decoded.len(), tris.len());
+        for (tri, decoded) in tris.iter().zip(&decoded) {
+            assert_eq!(tri.attribute, decoded.attribute);
+            for (a, b) in tri.vertices.iter().zip(decoded.vertices) {
+                assert!(a.distance(b) < 0.05, "{a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn root_blocks_match_a_full_build() {
+        // Binning the triangles per root block must not change which prisms end up in a cube
+        let tris = bumpy_grid();
+        let kcl = KCL::from_triangles(&tris).expect("Should encode");
+        let prism_tris: Vec<_> = tris.iter().map(|tri| tri.vertices).collect();
+        let all: Vec<u16> = (1..=prism_tris.len() as u16).collect();
+
+        let Octree::Branch(root) = &kcl.octree else {
+            panic!("The root should be a branch");
+        };
+        let blocks = [
+            kcl.area_x_blocks_shift,
+            kcl.area_xy_blocks_shift - kcl.area_x_blocks_shift,
+            kcl.area_width_mask[2].trailing_zeros() - kcl.block_width_shift,
+        ];
+        assert_eq!(root.len(), 1 << (blocks[0] + blocks[1] + blocks[2]));
+
+        let block_width = (1u32 << kcl.block_width_shift) as f32;
+        let mut expected = Vec::new();
+        for z in 0..(1u32 << blocks[2]) {
+            for y in 0..(1u32 << blocks[1]) {
+                for x in 0..(1u32 << blocks[0]) {
+                    let offset = Vec3::new(x as f32, y as f32, z as f32) * block_width;
+                    expected.push(Octree::build(
+                        &prism_tris,
+                        &all,
+                        kcl.area_min_pos + offset,
+                        kcl.block_width_shift,
+                    ));
+                }
+            }
+        }
+        assert_eq!(root, &expected);
+    }
+
+    #[test]
+    fn rejects_degenerate_triangles() {
+        let mut tris = bumpy_grid();
+        let v = tris[5].vertices[0];
+        tris[5].vertices = [v, v + Vec3::X, v + Vec3::X * 2.0];
+
+        let err = KCL::from_triangles(&tris).expect_err("Should not skip the triangle");
+        assert!(err.to_string().contains("Triangle 5"), "{err}");
+    }
+}
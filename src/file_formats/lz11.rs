@@ -0,0 +1,312 @@
+use std::io::{Error, ErrorKind};
+
+const LZ11_MAGIC: u8 = 0x11;
+
+// A back reference can only look 0x1000 bytes into the past
+const WINDOW_SIZE: usize = 0x1000;
+
+// Match lengths are split into three encodings depending on how large they are
+const MIN_MATCH: usize = 0x3;
+const SHORT_MATCH_MAX: usize = 0x10;
+const MEDIUM_MATCH_MAX: usize = 0x110;
+const LONG_MATCH_MAX: usize = 0x10110;
+
+const HASH_SIZE: usize = 1 << 16;
+
+fn invalid_data(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(Error::new(ErrorKind::InvalidData, msg))
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                               Decompression                                                       //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub fn decompress(src: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if src.len() < 4 || src[0] != LZ11_MAGIC {
+        return Err(invalid_data("Invalid Magic"));
+    }
+
+    // The size is a 24 bit little endian value. If it is zero, a 32 bit size follows
+    let mut size = u32::from_le_bytes([src[1], src[2], src[3], 0]) as usize;
+    let mut pos = 4;
+    if size == 0 {
+        if src.len() < 8 {
+            return Err(invalid_data("Missing Extended Size"));
+        }
+        size = u32::from_le_bytes([src[4], src[5], src[6], src[7]]) as usize;
+        pos = 8;
+    }
+
+    let mut out = Vec::with_capacity(size);
+    let mut next_byte = || -> Result<u8, Box<dyn std::error::Error>> {
+        let byte = *src
+            .get(pos)
+            .ok_or_else(|| invalid_data("Unexpected end of compressed data"))?;
+        pos += 1;
+        Ok(byte)
+    };
+
+    while out.len() < size {
+        let flags = next_byte()?;
+        for bit in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+
+            // Uncompressed byte
+            if flags & (1 << bit) == 0 {
+                out.push(next_byte()?);
+                continue;
+            }
+
+            // Back reference, the top nibble of the first byte decides the encoding
+            let b0 = next_byte()? as usize;
+            let (length, disp) = match b0 >> 4 {
+                0 => {
+                    let b1 = next_byte()? as usize;
+                    let b2 = next_byte()? as usize;
+                    (
+                        (((b0 & 0xF) << 4) | (b1 >> 4)) + SHORT_MATCH_MAX + 1,
+                        (((b1 & 0xF) << 8) | b2) + 1,
+                    )
+                }
+                1 => {
+                    let b1 = next_byte()? as usize;
+                    let b2 = next_byte()? as usize;
+                    let b3 = next_byte()? as usize;
+                    (
+                        (((b0 & 0xF) << 12) | (b1 << 4) | (b2 >> 4)) + MEDIUM_MATCH_MAX + 1,
+                        (((b2 & 0xF) << 8) | b3) + 1,
+                    )
+                }
+                _ => {
+                    let b1 = next_byte()? as usize;
+                    ((b0 >> 4) + 1, (((b0 & 0xF) << 8) | b1) + 1)
+                }
+            };
+
+            if disp > out.len() {
+                return Err(invalid_data(
+                    "Back reference is before the start of the data",
+                ));
+            }
+
+            // The copy may overlap itself, so it needs to be done byte by byte
+            let start = out.len() - disp;
+            for i in 0..length {
+                if out.len() >= size {
+                    break;
+                }
+                out.push(out[start + i]);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                                Compression                                                        //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn hash(src: &[u8], pos: usize) -> usize {
+    ((src[pos] as usize) << 8 ^ (src[pos + 1] as usize) << 4 ^ src[pos + 2] as usize)
+        & (HASH_SIZE - 1)
+}
+
+fn insert(src: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+    if pos + MIN_MATCH <= src.len() {
+        let h = hash(src, pos);
+        prev[pos] = head[h];
+        head[h] = pos;
+    }
+}
+
+// Finds the longest match for `pos` within the window. Returns (length, displacement)
+fn find_match(src: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    let mut best = (0, 0);
+    if pos + MIN_MATCH > src.len() {
+        return best;
+    }
+
+    let max_len = LONG_MATCH_MAX.min(src.len() - pos);
+    let mut candidate = head[hash(src, pos)];
+    while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE {
+        let len = src[candidate..]
+            .iter()
+            .zip(&src[pos..pos + max_len])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if len > best.0 {
+            best = (len, pos - candidate);
+            if len == max_len {
+                break;
+            }
+        }
+        candidate = prev[candidate];
+    }
+
+    if best.0 < MIN_MATCH {
+        (0, 0)
+    } else {
+        best
+    }
+}
+
+#[allow(dead_code)] // Nothing writes archives back yet, the tests cover it
+pub fn compress(src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(src.len() / 2 + 8);
+
+    // Header, sizes that are zero or do not fit in 24 bits use the extended form
+    out.push(LZ11_MAGIC);
+    if !src.is_empty() && src.len() <= 0xFF_FFFF {
+        out.extend_from_slice(&(src.len() as u32).to_le_bytes()[0..3]);
+    } else {
+        out.extend_from_slice(&[0, 0, 0]);
+        out.extend_from_slice(&(src.len() as u32).to_le_bytes());
+    }
+
+    // Hash chains for finding matches: `head` is the last position seen with a hash and `prev`
+    //  links every position to the previous one with the same hash
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; src.len()];
+
+    let mut pos = 0;
+    while pos < src.len() {
+        let flag_pos = out.len();
+        out.push(0);
+
+        for bit in (0..8).rev() {
+            if pos >= src.len() {
+                break;
+            }
+
+            let (length, disp) = find_match(src, pos, &head, &prev);
+            if length == 0 {
+                out.push(src[pos]);
+                insert(src, pos, &mut head, &mut prev);
+                pos += 1;
+                continue;
+            }
+
+            out[flag_pos] |= 1 << bit;
+            let d = disp - 1;
+            if length <= SHORT_MATCH_MAX {
+                let l = length - 1;
+                out.push(((l << 4) | (d >> 8)) as u8);
+                out.push(d as u8);
+            } else if length <= MEDIUM_MATCH_MAX {
+                let l = length - (SHORT_MATCH_MAX + 1);
+                out.push((l >> 4) as u8);
+                out.push(((l << 4) | (d >> 8)) as u8);
+                out.push(d as u8);
+            } else {
+                let l = length - (MEDIUM_MATCH_MAX + 1);
+                out.push((0x10 | (l >> 12)) as u8);
+                out.push((l >> 4) as u8);
+                out.push(((l << 4) | (d >> 8)) as u8);
+                out.push(d as u8);
+            }
+
+            for i in pos..pos + length {
+                insert(src, i, &mut head, &mut prev);
+            }
+            pos += length;
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Text with repeats at every distance, mixed with noise that does not compress
+    fn sample_data() -> Vec<u8> {
+        let mut seed = 0x1234_5678u32;
+        let mut data = Vec::new();
+        for i in 0..0x3000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            if i % 3 == 0 {
+                data.push((seed >> 24) as u8);
+            } else {
+                data.extend_from_slice(b"Skyward Sword");
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = sample_data();
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn empty_input() {
+        // The size of zero can only be written in the extended form
+        let compressed = compress(&[]);
+        assert_eq!(compressed, [LZ11_MAGIC, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(decompress(&compressed).unwrap(), []);
+    }
+
+    #[test]
+    fn long_runs_use_the_extended_lengths() {
+        // One literal, then a single back reference of 0x20 bytes (0x11-0x110 form)
+        let data = vec![b'x'; 0x21];
+        let compressed = compress(&data);
+        assert_eq!(
+            compressed,
+            [0x11, 0x21, 0x00, 0x00, 0x40, b'x', 0x00, 0xF0, 0x00]
+        );
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // One literal, then a single back reference of 0x220 bytes (0x111-0x10110 form)
+        let data = vec![b'x'; 0x221];
+        let compressed = compress(&data);
+        assert_eq!(
+            compressed,
+            [0x11, 0x21, 0x02, 0x00, 0x40, b'x', 0x10, 0x10, 0xF0, 0x00]
+        );
+        assert_eq!(decompress(&compressed).unwrap(), data);
+
+        // Longer than a single back reference can be
+        let data = vec![0u8; 0x20000];
+        assert_eq!(decompress(&compress(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn extended_size_header() {
+        let compressed = [0x11, 0, 0, 0, 0x03, 0, 0, 0, 0x00, b'a', b'b', b'c'];
+        assert_eq!(decompress(&compressed).unwrap(), b"abc");
+
+        let data = vec![7u8; 0x100_0000];
+        let compressed = compress(&data);
+        assert_eq!(compressed[..8], [0x11, 0, 0, 0, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn decodes_known_vectors() {
+        // Vectors encoded by hand from the LZ11 format: "abc" and then a 9 byte back reference
+        let compressed = [0x11, 0x0C, 0x00, 0x00, 0x10, b'a', b'b', b'c', 0x80, 0x02];
+        assert_eq!(decompress(&compressed).unwrap(), b"abcabcabcabc");
+
+        // A literal, then the 0x20 and the 0x200 byte forms
+        let compressed = [
+            0x11, 0x21, 0x02, 0x00, 0x60, b'x', 0x00, 0xF0, 0x00, 0x10, 0x0E, 0xF0, 0x00,
+        ];
+        assert_eq!(decompress(&compressed).unwrap(), vec![b'x'; 0x221]);
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        assert!(decompress(&[0x10, 0x03, 0x00, 0x00]).is_err());
+        assert!(decompress(&[0x11, 0x03, 0x00, 0x00, 0x00, b'a']).is_err());
+        // Back reference before the start of the data
+        assert!(decompress(&[0x11, 0x03, 0x00, 0x00, 0x80, 0x20, 0x00]).is_err());
+    }
+}
@@ -1,7 +1,12 @@
+pub mod bzs;
 pub mod dzb;
 pub mod kcl;
+pub mod lz11;
 pub mod plc;
+pub mod u8arc;
 
+pub use bzs::{LayerData, BZS};
 pub use dzb::DZB;
 pub use kcl::KCL;
 pub use plc::{PLCEntry, PLC};
+pub use u8arc::U8Archive;
@@ -1,5 +1,7 @@
-use byteorder::{ReadBytesExt, BE};
-use std::io::{Error, ErrorKind, Read, Seek};
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use std::io::{Error, ErrorKind, Read, Seek, Write};
+
+const PLC_ENTRY_SIZE: u16 = 0x14;
 
 #[derive(Debug, Clone)]
 pub struct PLCEntry {
@@ -26,7 +28,7 @@ impl PLC {
         let entry_size = reader.read_u16::<BE>()?;
         let num_entries = reader.read_u16::<BE>()?;
 
-        if entry_size != 0x14 {
+        if entry_size != PLC_ENTRY_SIZE {
             return Err(Box::new(Error::new(
                 ErrorKind::InvalidData,
                 "Invalid Entry Size",
@@ -48,4 +50,61 @@ impl PLC {
 
         Ok(Self { entries })
     }
+
+    #[allow(dead_code)]
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+        let num_entries: u16 = self.entries.len().try_into().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Too many entries to fit in a PLC file",
+            )
+        })?;
+
+        writer.write_all(b"SPLC")?;
+        writer.write_u16::<BE>(PLC_ENTRY_SIZE)?;
+        writer.write_u16::<BE>(num_entries)?;
+
+        for entry in &self.entries {
+            for code in entry.codes {
+                writer.write_u32::<BE>(code)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, io::Cursor};
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let plc = PLC::from_file(&mut Cursor::new(data)).expect("Should be a valid PLC");
+        let mut out = Vec::new();
+        plc.write_to(&mut out).expect("Should write");
+        out
+    }
+
+    #[test]
+    fn round_trip_is_byte_identical() {
+        let data = include_bytes!("../../test_data/sample.plc");
+        let plc = PLC::from_file(&mut Cursor::new(data)).expect("Should be a valid PLC");
+        assert_eq!(plc.entries.len(), 3);
+        assert_eq!(plc.entries[1].codes[1], 0x01F0_0000);
+        assert_eq!(round_trip(data), data);
+    }
+
+    #[test]
+    #[ignore = "needs the preprocessed game files in `Collision Files`"]
+    fn round_trip_real_files() {
+        let plcs = glob::glob("Collision Files/**/*.plc").expect("Invalid Glob pattern");
+        let mut count = 0;
+        for path in plcs.flatten() {
+            let data = fs::read(&path).unwrap();
+            assert_eq!(round_trip(&data), data, "{}", path.display());
+            count += 1;
+        }
+        assert!(count > 0, "No PLC files found in `Collision Files`");
+    }
 }
@@ -0,0 +1,382 @@
+use byteorder::{ReadBytesExt, BE};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+
+const U8_MAGIC: [u8; 4] = [0x55, 0xAA, 0x38, 0x2D];
+const U8_NODE_SIZE: u32 = 0x0C;
+
+struct RawNode {
+    is_dir: bool,
+    name_offset: u32,
+    // For Directories this is the parent index, for files it is the data offset
+    value1: u32,
+    // For Directories this is the index after the last child, for files it is the data length
+    value2: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum U8Node {
+    Dir { name: String, children: Vec<U8Node> },
+    File { name: String, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone)]
+pub struct U8Archive {
+    pub root: U8Node,
+}
+
+impl U8Archive {
+    pub fn from_file<R: Seek + Read>(reader: &mut R) -> Result<Self, Box<dyn std::error::Error>> {
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                    Read Header                                        //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        // Sizes read from the nodes are checked against this before anything is allocated
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+
+        if magic != U8_MAGIC {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidData,
+                "Invalid Magic",
+            )));
+        }
+
+        // The size of the node table and the data offset are not needed, the nodes give both
+        let first_node_offset = reader.read_u32::<BE>()?;
+        let _all_node_size = reader.read_u32::<BE>()?;
+        let _data_offset = reader.read_u32::<BE>()?;
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                    Read Nodes                                         //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        // The root node is always a directory and its end index is the total node count
+        reader.seek(SeekFrom::Start(first_node_offset as _))?;
+        let root = Self::read_raw_node(reader)?;
+        if !root.is_dir || root.name_offset != 0 {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidData,
+                "Invalid Root Node",
+            )));
+        }
+
+        let num_nodes = root.value2;
+        if u64::from(first_node_offset) + u64::from(num_nodes) * u64::from(U8_NODE_SIZE)
+            > stream_len
+        {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidData,
+                "Node table is out of bounds",
+            )));
+        }
+        let mut raw_nodes = Vec::with_capacity(num_nodes as usize);
+        raw_nodes.push(root);
+        for _ in 1..num_nodes {
+            raw_nodes.push(Self::read_raw_node(reader)?);
+        }
+
+        // The string pool directly follows the node table
+        let string_pool_offset = first_node_offset + num_nodes * U8_NODE_SIZE;
+
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        //                                    Build Tree                                         //
+        ///////////////////////////////////////////////////////////////////////////////////////////
+
+        let children = Self::build_dir(
+            reader,
+            &raw_nodes,
+            string_pool_offset,
+            stream_len,
+            1,
+            num_nodes,
+        )?;
+
+        Ok(Self {
+            root: U8Node::Dir {
+                name: String::new(),
+                children,
+            },
+        })
+    }
+
+    fn read_raw_node<R: Seek + Read>(
+        reader: &mut R,
+    ) -> Result<RawNode, Box<dyn std::error::Error>> {
+        let node_type = reader.read_u8()?;
+        let name_offset = reader.read_u24::<BE>()?;
+
+        let is_dir = match node_type {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(Box::new(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown Node Type: {node_type}"),
+                )))
+            }
+        };
+
+        Ok(RawNode {
+            is_dir,
+            name_offset,
+            value1: reader.read_u32::<BE>()?,
+            value2: reader.read_u32::<BE>()?,
+        })
+    }
+
+    // Builds all the nodes in [start, end) that belong to the same directory
+    fn build_dir<R: Seek + Read>(
+        reader: &mut R,
+        raw_nodes: &[RawNode],
+        string_pool_offset: u32,
+        stream_len: u64,
+        start: u32,
+        end: u32,
+    ) -> Result<Vec<U8Node>, Box<dyn std::error::Error>> {
+        let mut children = Vec::new();
+
+        let mut index = start;
+        while index < end {
+            let raw = raw_nodes
+                .get(index as usize)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Node index is out of bounds"))?;
+
+            // Names are null terminated strings in the string pool
+            reader.seek(SeekFrom::Start((string_pool_offset + raw.name_offset) as _))?;
+            let mut name_buf = Vec::new();
+            loop {
+                let c = reader.read_u8()?;
+                if c == 0 {
+                    break;
+                }
+                name_buf.push(c);
+            }
+            let name = String::from_utf8(name_buf)?;
+
+            if raw.is_dir {
+                // A directory owns every node up until its end index
+                if raw.value2 <= index || raw.value2 > end {
+                    return Err(Box::new(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Directory `{name}` has an invalid end index"),
+                    )));
+                }
+                let dir_children = Self::build_dir(
+                    reader,
+                    raw_nodes,
+                    string_pool_offset,
+                    stream_len,
+                    index + 1,
+                    raw.value2,
+                )?;
+                children.push(U8Node::Dir {
+                    name,
+                    children: dir_children,
+                });
+                index = raw.value2;
+            } else {
+                if u64::from(raw.value1) + u64::from(raw.value2) > stream_len {
+                    return Err(Box::new(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("File `{name}` is out of bounds"),
+                    )));
+                }
+                let mut data = vec![0u8; raw.value2 as usize];
+                reader.seek(SeekFrom::Start(raw.value1 as _))?;
+                reader.read_exact(&mut data)?;
+                children.push(U8Node::File { name, data });
+                index += 1;
+            }
+        }
+
+        Ok(children)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                               Path Lookup                                                         //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl U8Node {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Dir { name, .. } | Self::File { name, .. } => name,
+        }
+    }
+
+    /// The direct children of a directory. Directories named "." are looked through
+    pub fn children(&self) -> Vec<&U8Node> {
+        let mut out = Vec::new();
+        if let Self::Dir { children, .. } = self {
+            for child in children {
+                match child {
+                    Self::Dir { name, .. } if name == "." => out.extend(child.children()),
+                    _ => out.push(child),
+                }
+            }
+        }
+        out
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        match self {
+            Self::File { data, .. } => Some(data),
+            Self::Dir { .. } => None,
+        }
+    }
+
+    /// Finds the node at `path` relative to this node (ex: "rarc/Room_r00.arc")
+    pub fn get(&self, path: &str) -> Option<&U8Node> {
+        let mut node = self;
+        for part in path.split('/').filter(|p| !p.is_empty() && *p != ".") {
+            node = node.children().into_iter().find(|n| n.name() == part)?;
+        }
+        Some(node)
+    }
+
+    fn collect_files<'a>(&'a self, prefix: &str, out: &mut Vec<(String, &'a [u8])>) {
+        for child in self.children() {
+            let path = format!("{prefix}/{}", child.name());
+            match child {
+                Self::Dir { .. } => child.collect_files(&path, out),
+                Self::File { data, .. } => out.push((path, data)),
+            }
+        }
+    }
+}
+
+impl U8Archive {
+    pub fn get_node(&self, path: &str) -> Option<&U8Node> {
+        self.root.get(path)
+    }
+
+    pub fn get_file(&self, path: &str) -> Option<&[u8]> {
+        self.get_node(path)?.data()
+    }
+
+    /// Every file in the archive as `(path, data)`. Paths start with a '/' (ex: "/dat/room.plc")
+    pub fn files(&self) -> impl Iterator<Item = (String, &[u8])> {
+        let mut out = Vec::new();
+        self.root.collect_files("", &mut out);
+        out.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Directories are (name, parent index, end index), files are (name, data)
+    enum TestNode<'a> {
+        Dir(&'a str, u32, u32),
+        File(&'a str, &'a [u8]),
+    }
+
+    // Lays out the header, the node table, the string pool and then the file data
+    fn build_archive(nodes: &[TestNode]) -> Vec<u8> {
+        let name = |node: &TestNode| match node {
+            TestNode::Dir(name, ..) | TestNode::File(name, _) => name.to_string(),
+        };
+        let mut strings = Vec::new();
+        let name_offsets: Vec<u32> = nodes
+            .iter()
+            .map(|node| {
+                let offset = strings.len() as u32;
+                strings.extend_from_slice(name(node).as_bytes());
+                strings.push(0);
+                offset
+            })
+            .collect();
+
+        let first_node_offset = 0x20u32;
+        let all_node_size = nodes.len() as u32 * U8_NODE_SIZE + strings.len() as u32;
+        let data_offset = first_node_offset + all_node_size;
+
+        let mut out = U8_MAGIC.to_vec();
+        for value in [first_node_offset, all_node_size, data_offset] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        out.resize(first_node_offset as usize, 0);
+
+        let mut data = Vec::new();
+        for (node, name_offset) in nodes.iter().zip(name_offsets) {
+            let (node_type, value1, value2) = match node {
+                TestNode::Dir(_, parent, end) => (1u32, *parent, *end),
+                TestNode::File(_, file) => {
+                    let offset = data_offset + data.len() as u32;
+                    data.extend_from_slice(file);
+                    (0, offset, file.len() as u32)
+                }
+            };
+            out.extend_from_slice(&(node_type << 24 | name_offset).to_be_bytes());
+            out.extend_from_slice(&value1.to_be_bytes());
+            out.extend_from_slice(&value2.to_be_bytes());
+        }
+        out.extend_from_slice(&strings);
+        out.extend_from_slice(&data);
+        out
+    }
+
+    fn test_archive() -> U8Archive {
+        let data = build_archive(&[
+            TestNode::Dir("", 0, 7),
+            TestNode::Dir(".", 0, 7),
+            TestNode::Dir("dat", 1, 6),
+            TestNode::File("room.plc", b"SPLC"),
+            TestNode::Dir("sub", 2, 6),
+            TestNode::File("a.bin", &[1, 2, 3]),
+            TestNode::File("top.bin", &[]),
+        ]);
+        U8Archive::from_file(&mut Cursor::new(data)).expect("Should be a valid archive")
+    }
+
+    #[test]
+    fn looks_through_the_root_entry() {
+        let archive = test_archive();
+        let names: Vec<&str> = archive.root.children().iter().map(|n| n.name()).collect();
+        assert_eq!(names, ["dat", "top.bin"]);
+        assert_eq!(archive.get_file("./dat/room.plc"), Some(&b"SPLC"[..]));
+    }
+
+    #[test]
+    fn finds_nested_nodes() {
+        let archive = test_archive();
+        assert_eq!(archive.get_file("dat/sub/a.bin"), Some(&[1u8, 2, 3][..]));
+        assert_eq!(archive.get_file("top.bin"), Some(&[][..]));
+        assert!(matches!(
+            archive.get_node("dat/sub"),
+            Some(U8Node::Dir { .. })
+        ));
+
+        // Directories have no data and missing paths are not found
+        assert_eq!(archive.get_file("dat/sub"), None);
+        assert!(archive.get_node("dat/missing.bin").is_none());
+        assert!(archive.get_file("sub/a.bin").is_none());
+
+        let files: Vec<String> = archive.files().map(|(path, _)| path).collect();
+        assert_eq!(files, ["/dat/room.plc", "/dat/sub/a.bin", "/top.bin"]);
+    }
+
+    #[test]
+    fn rejects_invalid_archives() {
+        assert!(U8Archive::from_file(&mut Cursor::new(b"RARC0000")).is_err());
+        let data = build_archive(&[TestNode::Dir("", 0, 2), TestNode::Dir("dat", 0, 5)]);
+        assert!(U8Archive::from_file(&mut Cursor::new(data)).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_sizes() {
+        // File length past the end of the archive
+        let mut data = build_archive(&[TestNode::Dir("", 0, 2), TestNode::File("a", b"abc")]);
+        data[0x34..0x38].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(U8Archive::from_file(&mut Cursor::new(data)).is_err());
+
+        // Node count past the end of the archive
+        let mut data = build_archive(&[TestNode::Dir("", 0, 1)]);
+        data[0x28..0x2C].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(U8Archive::from_file(&mut Cursor::new(data)).is_err());
+    }
+}
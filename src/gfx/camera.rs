@@ -154,6 +154,23 @@ impl Camera {
         self.up = up.normalize();
         self.dirty = true;
     }
+
+    pub fn set_yaw(&mut self, yaw: f32) {
+        self.yaw = yaw;
+        self.dirty = true;
+    }
+
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.pitch = pitch.clamp(-89.0, 89.0);
+        self.dirty = true;
+    }
+
+    /// Turns the camera towards `target`. The front is derived from the yaw and pitch
+    pub fn look_at(&mut self, target: Vec3) {
+        let dir = (target - self.pos).normalize_or(self.front);
+        self.set_yaw(dir.z.atan2(dir.x).to_degrees());
+        self.set_pitch(dir.y.asin().to_degrees());
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -1 +1,173 @@
+use std::{mem::offset_of, ptr::slice_from_raw_parts};
 
+use eframe::glow;
+use glam::{Mat4, Vec3, Vec4};
+
+use super::{Model, Shader, Vertex};
+
+/// A list of vertices drawn with a single primitive type (ex: glow::TRIANGLES)
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub verts: Vec<Vertex>,
+    mode: u32,
+    vao: Option<glow::VertexArray>,
+    vbo: Option<glow::Buffer>,
+}
+
+impl Mesh {
+    pub fn new(verts: Vec<Vertex>, mode: u32) -> Self {
+        Self {
+            verts,
+            mode,
+            vao: None,
+            vbo: None,
+        }
+    }
+
+    /// Draws `count` vertices starting at `first`
+    pub fn draw_range(&self, gl: &glow::Context, first: usize, count: usize) {
+        if self.vao.is_none() || self.vbo.is_none() {
+            return;
+        }
+
+        unsafe {
+            use glow::HasContext as _;
+
+            gl.bind_vertex_array(self.vao);
+            gl.draw_arrays(self.mode, first as _, count as _);
+        }
+    }
+}
+
+/// Vertices pushed by `push_box`
+pub const BOX_VERTS: usize = 36;
+
+/// Pushes the triangles of the unit cube ([-0.5, 0.5] on every axis) transformed by `mtx`.
+///  `clr` gives the color of a face from its normal in local space
+pub fn push_box(verts: &mut Vec<Vertex>, mtx: &Mat4, clr: impl Fn(Vec3) -> Vec4) {
+    // (normal, up, right) of each face
+    let faces = [
+        (Vec3::X, Vec3::Y, Vec3::Z),
+        (Vec3::NEG_X, Vec3::Y, Vec3::NEG_Z),
+        (Vec3::Y, Vec3::Z, Vec3::X),
+        (Vec3::NEG_Y, Vec3::NEG_Z, Vec3::X),
+        (Vec3::Z, Vec3::Y, Vec3::NEG_X),
+        (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+    ];
+    for (nrm, up, right) in faces {
+        let center = nrm * 0.5;
+        let corners = [
+            center - up * 0.5 - right * 0.5,
+            center - up * 0.5 + right * 0.5,
+            center + up * 0.5 + right * 0.5,
+            center + up * 0.5 - right * 0.5,
+        ];
+        let face_clr = clr(nrm);
+        let world_nrm = mtx.transform_vector3(nrm);
+        for i in [0, 1, 2, 0, 2, 3] {
+            verts.push(Vertex::new(
+                mtx.transform_point3(corners[i]),
+                world_nrm,
+                face_clr,
+            ));
+        }
+    }
+}
+
+impl Model for Mesh {
+    fn setup_gl(&mut self, gl: &glow::Context) {
+        // Do not setup twice!
+        if self.vao.is_some() || self.vbo.is_some() {
+            panic!("Trying to setup GL Twice");
+        }
+
+        unsafe {
+            use glow::HasContext as _;
+
+            // Create Vertex Array and Vertex Buffer
+            match gl.create_vertex_array() {
+                Ok(vao) => self.vao = Some(vao),
+                Err(e) => panic!("{}", e),
+            };
+            match gl.create_buffer() {
+                Ok(vbo) => self.vbo = Some(vbo),
+                Err(e) => panic!("{}", e),
+            };
+
+            gl.bind_vertex_array(self.vao);
+            gl.bind_buffer(glow::ARRAY_BUFFER, self.vbo);
+
+            let bind_data = slice_from_raw_parts(
+                self.verts.as_ptr() as *const u8,
+                self.verts.len() * size_of::<Vertex>(),
+            )
+            .as_ref()
+            .unwrap();
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bind_data, glow::STATIC_DRAW);
+
+            gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, size_of::<Vertex>() as _, 0);
+
+            gl.enable_vertex_attrib_array(1);
+            gl.vertex_attrib_pointer_f32(
+                1,
+                3,
+                glow::FLOAT,
+                false,
+                size_of::<Vertex>() as _,
+                offset_of!(Vertex, nrm) as _,
+            );
+
+            gl.enable_vertex_attrib_array(2);
+            gl.vertex_attrib_pointer_f32(
+                2,
+                4,
+                glow::FLOAT,
+                false,
+                size_of::<Vertex>() as _,
+                offset_of!(Vertex, clr) as _,
+            );
+
+            gl.bind_vertex_array(None)
+        }
+    }
+
+    fn destroy_gl(&mut self, gl: &glow::Context) {
+        unsafe {
+            use glow::HasContext as _;
+
+            if let (Some(vao), Some(vbo)) = (self.vao, self.vbo) {
+                gl.delete_vertex_array(vao);
+                gl.delete_buffer(vbo);
+            }
+
+            self.vao = None;
+            self.vbo = None;
+        }
+    }
+
+    fn update_gl(&mut self, gl: &glow::Context) {
+        unsafe {
+            use glow::HasContext as _;
+
+            if self.vao.is_some() && self.vbo.is_some() {
+                gl.bind_buffer(glow::ARRAY_BUFFER, self.vbo);
+                let bind_data = slice_from_raw_parts::<u8>(
+                    self.verts.as_ptr() as *const u8,
+                    self.verts.len() * size_of::<Vertex>(),
+                )
+                .as_ref()
+                .unwrap();
+
+                gl.buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, 0, bind_data);
+            }
+        }
+    }
+
+    fn draw(&mut self, gl: &glow::Context, shader: &Shader) {
+        // Meshes are usually part of a stage. All uniforms should belong to the stage
+        let _ = shader;
+
+        self.draw_range(gl, 0, self.verts.len());
+    }
+}
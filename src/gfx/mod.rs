@@ -3,6 +3,7 @@ pub mod mesh;
 pub mod shader;
 pub mod vertex;
 
+pub use mesh::Mesh;
 pub use shader::Shader;
 pub use vertex::Vertex;
 
@@ -1,18 +1,23 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 #![allow(unsafe_code)]
 #![allow(clippy::undocumented_unsafe_blocks)]
+#![allow(clippy::upper_case_acronyms)]
 
 use eframe::{egui, egui_glow, glow};
 use egui::mutex::Mutex;
 use egui::panel::Side;
 use egui::{Color32, Id, Response};
-use ss_viewer::plc::{EntryType, ENTRY_FILTER};
+use ss_viewer::entrance_graph::EntranceGraph;
+use ss_viewer::plc::PLCSchema;
+use ss_viewer::plc_filter::PLCFilter;
 use ss_viewer::scene::Scene;
 // use stage_model::Stage;
 
 use core::f32;
+use std::collections::BTreeMap;
 use std::fs;
 use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 mod file_formats;
@@ -26,6 +31,7 @@ const WIDTH: f32 = 1600f32;
 const HEIGHT: f32 = 900f32;
 
 const COLLISION_SRC_DIR: &str = "Collision Files";
+const PLC_SCHEMA_PATH: &str = "plc_schema.toml";
 
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
@@ -56,8 +62,15 @@ struct MyApp {
     nrm_shader: Shader,
     black_shader: Shader,
     cam_speed: f32,
-    property_entry: usize,
-    range_selection: u32,
+    plc_schema: PLCSchema,
+    property_entry: Option<usize>, // Into plc_schema.fields, None shows the normals
+    isolated_value: Option<u32>,   // Value of the property field to show alone
+    filter_text: String,
+    property_filter: Option<PLCFilter>, // None when the filter is empty or invalid
+    filter_error: Option<String>,
+    filter_matches: Vec<usize>, // Number of triangles matching the filter in each scene
+    histograms: Vec<Histogram>, // Of the property field, for each scene
+    histogram_model: Option<usize>, // Model of the histogram, None for the whole scene
     bg_color: Color32,
 }
 
@@ -95,11 +108,30 @@ impl MyApp {
 
         let mut scene_map: Vec<Scene> = Vec::new();
 
-        let stages =
-            glob::glob(format!("{COLLISION_SRC_DIR}/*").as_str()).expect("Invalid Glob pattern");
-        for stage_path in stages {
-            let stage_path = stage_path.expect("Glob Error Encountered");
-            scene_map.push(Scene::from_dir(stage_path).expect("Could not Read Stage"));
+        // A game extract (`<SS_EXTRACT>/DATA/files` or `<SSHD_EXTRACT>/romfs`) can be passed as the
+        //  first argument. Otherwise the preprocessed files in COLLISION_SRC_DIR are used
+        if let Some(extract_dir) = std::env::args().nth(1) {
+            let extract_dir = PathBuf::from(extract_dir);
+            let stages = glob::glob(format!("{}/Stage/*", extract_dir.display()).as_str())
+                .expect("Invalid Glob pattern");
+            for stage_path in stages {
+                let stage_path = stage_path.expect("Glob Error Encountered");
+                match Scene::from_game_extract(stage_path) {
+                    Ok(scene) => scene_map.push(scene),
+                    Err(e) => println!("Could not Read Stage: {e}"),
+                }
+            }
+            match Scene::from_object_pack(extract_dir) {
+                Ok(scene) => scene_map.push(scene),
+                Err(e) => println!("Could not Read Object Pack: {e}"),
+            }
+        } else {
+            let stages = glob::glob(format!("{COLLISION_SRC_DIR}/*").as_str())
+                .expect("Invalid Glob pattern");
+            for stage_path in stages {
+                let stage_path = stage_path.expect("Glob Error Encountered");
+                scene_map.push(Scene::from_dir(stage_path).expect("Could not Read Stage"));
+            }
         }
 
         ///////////////////////////////////////////////////////////////////////////////////////////
@@ -118,82 +150,294 @@ impl MyApp {
             nrm_shader,
             black_shader,
             cam_speed: 30f32,
-            property_entry: 0,
-            range_selection: 0,
+            plc_schema: PLCSchema::load(Path::new(PLC_SCHEMA_PATH)),
+            property_entry: None,
+            isolated_value: None,
+            filter_text: String::new(),
+            property_filter: None,
+            filter_error: None,
+            filter_matches: Vec::new(),
+            histograms: Vec::new(),
+            histogram_model: None,
             bg_color: Color32::from_rgb(10, 10, 10),
         }
     }
+
+    // Parses the filter text again, ex: after it was edited or the schema was reloaded
+    fn parse_filter(&mut self) {
+        self.property_filter = None;
+        self.filter_error = None;
+        if self.filter_text.trim().is_empty() {
+            return;
+        }
+        match PLCFilter::parse(&self.filter_text, &self.plc_schema) {
+            Ok(filter) => self.property_filter = Some(filter),
+            Err(e) => self.filter_error = Some(e.to_string()),
+        }
+    }
+
+    // Colors every scene by the property field and the filter
+    fn update_property_colors(&mut self, gl: &glow::Context) {
+        let property_field = self.property_entry.map(|i| &self.plc_schema.fields[i]);
+        self.filter_matches = self
+            .model
+            .iter()
+            .map(|scene| {
+                scene.lock().update_scene_property_filter(
+                    property_field,
+                    self.isolated_value,
+                    self.property_filter.as_ref(),
+                )
+            })
+            .collect();
+        self.histograms = self
+            .model
+            .iter()
+            .map(|scene| {
+                property_field.map_or_else(Vec::new, |field| scene.lock().property_histogram(field))
+            })
+            .collect();
+
+        if let Some(scene_index) = self.selected_scene {
+            self.model[scene_index].lock().update_gl(gl);
+        }
+    }
+
+    // Every value of the property field in the selected scene. Clicking one shows it alone
+    fn legend_ui(&mut self, ctx: &egui::Context, filter_changed: &mut bool) {
+        let (Some(field_index), Some(scene_index)) = (self.property_entry, self.selected_scene)
+        else {
+            return;
+        };
+        let Some(histogram) = self.histograms.get(scene_index) else {
+            return;
+        };
+        let field = &self.plc_schema.fields[field_index];
+        let legend = histogram_counts(histogram, None);
+
+        egui::Window::new("Legend")
+            .default_pos([WIDTH - 260.0, 40.0])
+            .show(ctx, |ui| {
+                ui.label(&field.name);
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("Legend Grid").striped(true).show(ui, |ui| {
+                            for (value, count) in legend {
+                                let [r, g, b, _] = field.color(value).to_array();
+                                let (rect, _) = ui.allocate_exact_size(
+                                    egui::vec2(14.0, 14.0),
+                                    egui::Sense::hover(),
+                                );
+                                ui.painter()
+                                    .rect_filled(rect, 2.0, egui::Rgba::from_rgb(r, g, b));
+                                let isolated = self.isolated_value == Some(value);
+                                if ui
+                                    .selectable_label(isolated, field.value_name(value))
+                                    .on_hover_text(
+                                        "Click to show only this value, again to show all",
+                                    )
+                                    .clicked()
+                                {
+                                    self.isolated_value = (!isolated).then_some(value);
+                                    *filter_changed = true;
+                                }
+                                ui.label(format!("{count} tris"));
+                                ui.end_row();
+                            }
+                        });
+                    });
+            });
+    }
+
+    // Bars of the triangle count of each value of the property field. Clicking one shows it alone
+    fn histogram_ui(&mut self, ctx: &egui::Context, filter_changed: &mut bool) {
+        let (Some(field_index), Some(scene_index)) = (self.property_entry, self.selected_scene)
+        else {
+            return;
+        };
+        let Some(histogram) = self.histograms.get(scene_index) else {
+            return;
+        };
+        let field = &self.plc_schema.fields[field_index];
+        // The model may be from the previously selected scene
+        if self
+            .histogram_model
+            .is_some_and(|model| model >= histogram.len())
+        {
+            self.histogram_model = None;
+        }
+        let model_name = |model: Option<usize>| match model.and_then(|i| histogram.get(i)) {
+            Some((name, _)) => name.clone(),
+            None => "All Models".to_string(),
+        };
+
+        egui::Window::new("Histogram")
+            .default_pos([WIDTH - 560.0, HEIGHT - 260.0])
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label(&field.name)
+                    .selected_text(model_name(self.histogram_model))
+                    .show_ui(ui, |ui| {
+                        let models = std::iter::once(None).chain((0..histogram.len()).map(Some));
+                        for model in models {
+                            ui.selectable_value(
+                                &mut self.histogram_model,
+                                model,
+                                model_name(model),
+                            );
+                        }
+                    });
+                let counts = histogram_counts(histogram, self.histogram_model);
+                let max_count = counts.iter().map(|&(_, count)| count).max().unwrap_or(1);
+
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    let size = egui::vec2(
+                        (counts.len() as f32 * HISTOGRAM_BAR_WIDTH).max(ui.available_width()),
+                        HISTOGRAM_HEIGHT,
+                    );
+                    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                    for (i, &(value, count)) in counts.iter().enumerate() {
+                        let height = rect.height() * count as f32 / max_count as f32;
+                        let left = rect.left() + i as f32 * HISTOGRAM_BAR_WIDTH;
+                        let column = egui::Rect::from_min_max(
+                            egui::pos2(left, rect.top()),
+                            egui::pos2(left + HISTOGRAM_BAR_WIDTH - 2.0, rect.bottom()),
+                        );
+                        let bar = column.with_min_y(rect.bottom() - height.max(1.0));
+
+                        let [r, g, b, _] = field.color(value).to_array();
+                        ui.painter()
+                            .rect_filled(bar, 0.0, egui::Rgba::from_rgb(r, g, b));
+                        let isolated = self.isolated_value == Some(value);
+                        if isolated {
+                            ui.painter().rect_stroke(
+                                column,
+                                0.0,
+                                egui::Stroke::new(1.0, Color32::WHITE),
+                            );
+                        }
+
+                        // The whole column can be clicked so small bars are easy to hit
+                        let response = ui
+                            .interact(
+                                column,
+                                ui.id().with(("Histogram Bar", value)),
+                                egui::Sense::click(),
+                            )
+                            .on_hover_text(format!("{}\n{count} tris", field.value_name(value)));
+                        if response.clicked() {
+                            self.isolated_value = (!isolated).then_some(value);
+                            *filter_changed = true;
+                        }
+                    }
+                });
+            });
+    }
+}
+
+// Triangle count of each value of the property field, for each collision model
+type Histogram = Vec<(String, BTreeMap<u32, usize>)>;
+
+const HISTOGRAM_BAR_WIDTH: f32 = 12.0;
+const HISTOGRAM_HEIGHT: f32 = 150.0;
+
+// Sums the counts of `model`, or of every model
+fn histogram_counts(histogram: &Histogram, model: Option<usize>) -> Vec<(u32, usize)> {
+    let mut counts = BTreeMap::new();
+    histogram
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| model.is_none_or(|model| model == i))
+        .for_each(|(_, (_, values))| {
+            values
+                .iter()
+                .for_each(|(&value, &count)| *counts.entry(value).or_insert(0) += count)
+        });
+    counts.into_iter().collect()
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let mut filter_changed = false;
         egui::SidePanel::new(Side::Left, Id::new("Control Panel")).show(ctx, |ui| {
             ui.add(egui::Checkbox::new(&mut self.wireframe, "Wireframe"));
             ui.add(egui::Slider::new(
                 &mut self.cam_speed,
                 RangeInclusive::new(0.0, 1000.0),
             ));
+            let field_name = |entry: Option<usize>| match entry {
+                Some(i) => self.plc_schema.fields[i].name.clone(),
+                None => "Normals".to_string(),
+            };
             egui::ComboBox::from_label("Property Filter")
-                .selected_text(format!(
-                    "{}",
-                    match &ENTRY_FILTER[self.property_entry] {
-                        EntryType::Norm => format!("Normals"),
-                        EntryType::Range(val) | EntryType::Single(val) => {
-                            format!("Code {}: 0x{:08X}", val.code_idx, val.mask << val.shift)
-                        }
-                    }
-                ))
+                .selected_text(field_name(self.property_entry))
                 .show_ui(ui, |ui| {
-                    for i in 0..ENTRY_FILTER.len() {
-                        let text = match &ENTRY_FILTER[i] {
-                            EntryType::Norm => format!("Normals"),
-                            EntryType::Range(val) | EntryType::Single(val) => {
-                                format!("Code {}: 0x{:08X}", val.code_idx, val.mask << val.shift)
-                            }
-                        };
+                    let entries =
+                        std::iter::once(None).chain((0..self.plc_schema.fields.len()).map(Some));
+                    for entry in entries {
                         if ui
-                            .selectable_value(&mut self.property_entry, i, text)
+                            .selectable_value(&mut self.property_entry, entry, field_name(entry))
                             .changed()
                         {
-                            self.model.iter().for_each(|scene| {
-                                let mut scene = scene.lock();
-                                scene.update_scene_property_filter(i, self.range_selection);
-                                scene.update_gl(frame.gl().unwrap());
-                            });
+                            self.isolated_value = None;
+                            filter_changed = true;
                         }
                     }
                 });
-            match &ENTRY_FILTER[self.property_entry] {
-                EntryType::Range(val) => {
-                    if ui
-                        .add(
-                            egui::Slider::new(&mut self.range_selection, 0..=val.mask)
-                                .clamp_to_range(true)
-                                .hexadecimal(2, false, true),
-                        )
-                        .changed()
-                    {
-                        self.model.iter().for_each(|scene| {
-                            let mut scene = scene.lock();
-                            scene.update_scene_property_filter(
-                                self.property_entry,
-                                self.range_selection,
-                            );
-                        });
-
-                        if let Some(scene_index) = self.selected_scene {
-                            self.model[scene_index]
-                                .lock()
-                                .update_gl(frame.gl().unwrap());
-                        }
+            if ui
+                .button("Reload Schema")
+                .on_hover_text(format!("Reads {PLC_SCHEMA_PATH} again"))
+                .clicked()
+            {
+                self.plc_schema = PLCSchema::load(Path::new(PLC_SCHEMA_PATH));
+                self.property_entry = self
+                    .property_entry
+                    .filter(|&i| i < self.plc_schema.fields.len());
+                self.parse_filter();
+                filter_changed = true;
+            }
+            ui.horizontal(|ui| {
+                ui.label("Filter");
+                if ui
+                    .text_edit_singleline(&mut self.filter_text)
+                    .on_hover_text("Highlights the triangles matching an expression over the schema fields\nex: ground == 5 && pass_link && !pass_camera")
+                    .changed()
+                {
+                    self.parse_filter();
+                    filter_changed = true;
+                }
+            });
+            match (&self.filter_error, self.selected_scene) {
+                (Some(e), _) => {
+                    ui.colored_label(Color32::RED, e);
+                }
+                (None, Some(scene_index)) if self.property_filter.is_some() => {
+                    // Counted on the next frame when the filter just changed
+                    if let Some(matches) = self.filter_matches.get(scene_index) {
+                        ui.label(format!("{matches} matching triangles"));
                     }
                 }
                 _ => {}
-            };
+            }
             ui.horizontal(|ui| {
                 ui.color_edit_button_srgba(&mut self.bg_color);
                 ui.label("BG Color");
             });
+            if ui
+                .button("Export Entrance Graph")
+                .on_hover_text("Writes entrance_graph.dot and entrance_graph.json")
+                .clicked()
+            {
+                let exits = self
+                    .model
+                    .iter()
+                    .flat_map(|scene| scene.lock().exits())
+                    .collect();
+                match EntranceGraph::new(exits).export(Path::new(".")) {
+                    Ok(()) => println!("Exported the entrance graph"),
+                    Err(e) => println!("Could not export the entrance graph: {e}"),
+                }
+            }
 
             ui.add(egui::Separator::default());
 
@@ -229,6 +473,12 @@ impl eframe::App for MyApp {
                     });
                 });
         });
+        self.legend_ui(ctx, &mut filter_changed);
+        self.histogram_ui(ctx, &mut filter_changed);
+        if filter_changed {
+            self.update_property_colors(frame.gl().unwrap());
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::Frame::canvas(ui.style())
                 .fill(self.bg_color)
@@ -262,7 +512,7 @@ impl MyApp {
         let cam = &mut scene.camera;
 
         ctx.input(|i| {
-            let amount = (self.cam_speed * i.predicted_dt as f32 * 2.0f32) as f32;
+            let amount = self.cam_speed * i.predicted_dt * 2.0f32;
             // let mut update_model = false;
 
             if i.key_down(egui::Key::W) {
@@ -310,6 +560,16 @@ impl MyApp {
         // Handle Input related things
         self.handle_input(ui, ctx, &response);
 
+        // Describe the actor under the mouse
+        if let Some(pointer) = response.hover_pos() {
+            let tooltip = self.model[self.selected_scene.unwrap()]
+                .lock()
+                .actor_tooltip(&proj, rect, pointer);
+            if let Some(tooltip) = tooltip {
+                response.on_hover_text_at_pointer(tooltip);
+            }
+        }
+
         // Clone to Give to callback
         let scene = self.model[self.selected_scene.unwrap()].clone();
         let shader = self.shader.clone();
@@ -370,5 +630,40 @@ impl MyApp {
             })),
         };
         ui.painter().add(callback);
+
+        // Marker and area labels are drawn over the scene
+        let marker_labels = self.model[self.selected_scene.unwrap()]
+            .lock()
+            .marker_labels(&proj, rect);
+        for (pos, text, color) in marker_labels {
+            ui.painter().text(
+                pos,
+                egui::Align2::LEFT_BOTTOM,
+                text,
+                egui::FontId::monospace(10.0),
+                color,
+            );
+        }
+        let labels = self.model[self.selected_scene.unwrap()].lock().area_labels(
+            &proj,
+            rect,
+            ui.ctx()
+                .pointer_hover_pos()
+                .filter(|pos| rect.contains(*pos)),
+        );
+        for (pos, text, highlighted) in labels {
+            let color = if highlighted {
+                Color32::YELLOW
+            } else {
+                Color32::LIGHT_GRAY
+            };
+            ui.painter().text(
+                pos,
+                egui::Align2::CENTER_CENTER,
+                text,
+                egui::FontId::monospace(12.0),
+                color,
+            );
+        }
     }
 }
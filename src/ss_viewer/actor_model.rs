@@ -0,0 +1,236 @@
+use crate::{
+    file_formats::{LayerData, BZS},
+    gfx::{
+        mesh::{push_box, BOX_VERTS},
+        Mesh, Model, Shader, Vertex,
+    },
+};
+use eframe::glow;
+use glam::{Mat4, Vec3, Vec4};
+
+// Size of the marker drawn for an actor with a scale of 1
+const ACTOR_MARKER_SIZE: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActorType {
+    OBJ,
+    OBJS,
+    SOBJ,
+    SOBS,
+    STAG,
+    STAS,
+    DOOR,
+}
+
+impl ActorType {
+    pub const ALL: [Self; 7] = [
+        Self::OBJ,
+        Self::OBJS,
+        Self::SOBJ,
+        Self::SOBS,
+        Self::STAG,
+        Self::STAS,
+        Self::DOOR,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OBJ => "OBJ",
+            Self::OBJS => "OBJS",
+            Self::SOBJ => "SOBJ",
+            Self::SOBS => "SOBS",
+            Self::STAG => "STAG",
+            Self::STAS => "STAS",
+            Self::DOOR => "DOOR",
+        }
+    }
+
+    fn color(&self) -> Vec4 {
+        match self {
+            Self::OBJ => Vec4::new(0.95, 0.35, 0.25, 1.0),
+            Self::OBJS => Vec4::new(0.95, 0.65, 0.20, 1.0),
+            Self::SOBJ => Vec4::new(0.30, 0.75, 0.95, 1.0),
+            Self::SOBS => Vec4::new(0.35, 0.45, 0.95, 1.0),
+            Self::STAG => Vec4::new(0.40, 0.90, 0.40, 1.0),
+            Self::STAS => Vec4::new(0.20, 0.60, 0.30, 1.0),
+            Self::DOOR => Vec4::new(0.85, 0.40, 0.90, 1.0),
+        }
+    }
+}
+
+/// Angles are 16 bit (0x10000 is a full turn) and are applied in the order Y, X, Z like the game
+pub fn rotation_mtx(angle: [i16; 3]) -> Mat4 {
+    let [x, y, z] = angle.map(|a| a as f32 * std::f32::consts::TAU / 65536.0);
+    Mat4::from_rotation_y(y) * Mat4::from_rotation_x(x) * Mat4::from_rotation_z(z)
+}
+
+/// A single placement of an actor in a stage or room
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub actor_type: ActorType,
+    pub name: String,
+    pub id: u16,
+    pub params1: u32,
+    pub params2: u32,
+    pub pos: Vec3,
+    pub angle: [i16; 3],
+    pub scale: Vec3,
+    pub layer: Option<usize>, // None when placed outside of the layers
+}
+
+impl Actor {
+    pub fn tooltip(&self) -> String {
+        let layer = match self.layer {
+            Some(layer) => format!("Layer {layer}"),
+            None => "No Layer".to_string(),
+        };
+        format!(
+            "{} ({}, {layer})\nid: 0x{:04X}\nparams1: 0x{:08X}\nparams2: 0x{:08X}\npos: ({:.1}, {:.1}, {:.1})\nangle: (0x{:04X}, 0x{:04X}, 0x{:04X})",
+            self.name,
+            self.actor_type.name(),
+            self.id,
+            self.params1,
+            self.params2,
+            self.pos.x,
+            self.pos.y,
+            self.pos.z,
+            self.angle[0] as u16,
+            self.angle[1] as u16,
+            self.angle[2] as u16,
+        )
+    }
+
+    pub fn transform(&self) -> Mat4 {
+        Mat4::from_translation(self.pos) * rotation_mtx(self.angle)
+    }
+
+    // A scale of 0 would make the actor invisible
+    fn visible_scale(&self) -> Vec3 {
+        self.scale.abs().map(|s| if s == 0.0 { 1.0 } else { s })
+    }
+
+    /// Places the collision of the object spawned by the actor
+    pub fn object_transform(&self) -> Mat4 {
+        self.transform() * Mat4::from_scale(self.visible_scale())
+    }
+
+    // Maps the unit cube to a marker standing on the position of the actor
+    fn marker_mtx(&self) -> Mat4 {
+        self.transform()
+            * Mat4::from_scale(self.visible_scale() * ACTOR_MARKER_SIZE)
+            * Mat4::from_translation(Vec3::new(0.0, 0.5, 0.0))
+    }
+
+    /// The center of the marker drawn for the actor
+    pub fn center(&self) -> Vec3 {
+        self.marker_mtx().transform_point3(Vec3::ZERO)
+    }
+
+    // The front (+Z) face is lighter to show the facing
+    fn build_verts(&self, verts: &mut Vec<Vertex>) {
+        let clr = self.actor_type.color();
+        push_box(verts, &self.marker_mtx(), |nrm| {
+            if nrm == Vec3::Z {
+                clr.lerp(Vec4::ONE, 0.6)
+            } else {
+                clr
+            }
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ActorModel {
+    pub name: String,
+    pub actors: Vec<Actor>,
+
+    // Rendering Information
+    pub render: bool,
+    mesh: Mesh,
+}
+
+impl ActorModel {
+    /// Collects every actor placed in the bzs, both outside and inside of the layers
+    pub fn from_bzs(name: String, bzs: &BZS) -> Self {
+        let mut actors = Vec::new();
+        Self::collect_actors(bzs.objects(), None, &mut actors);
+        for (i, layer) in bzs.layers().iter().enumerate() {
+            Self::collect_actors(layer, Some(i), &mut actors);
+        }
+
+        let mut verts = Vec::with_capacity(actors.len() * BOX_VERTS);
+        actors
+            .iter()
+            .for_each(|actor| actor.build_verts(&mut verts));
+
+        Self {
+            name,
+            actors,
+            render: true,
+            mesh: Mesh::new(verts, glow::TRIANGLES),
+        }
+    }
+
+    fn collect_actors(layer_data: &LayerData, layer: Option<usize>, actors: &mut Vec<Actor>) {
+        // Every type shares the same layout, only some of them have a scale (`size`)
+        macro_rules! push_actors {
+            ($entries:expr, $actor_type:expr) => {
+                push_actors!($entries, $actor_type, |_entry| Vec3::ONE)
+            };
+            ($entries:expr, $actor_type:expr, size) => {
+                push_actors!($entries, $actor_type, |entry| Vec3::from_array(entry.size))
+            };
+            ($entries:expr, $actor_type:expr, |$entry:ident| $scale:expr) => {
+                actors.extend($entries.iter().map(|$entry| Actor {
+                    actor_type: $actor_type,
+                    name: $entry.name.as_str().to_string(),
+                    id: $entry.id,
+                    params1: $entry.params1,
+                    params2: $entry.params2,
+                    pos: Vec3::from_array($entry.pos),
+                    angle: $entry.angle,
+                    scale: $scale,
+                    layer,
+                }))
+            };
+        }
+        push_actors!(layer_data.obj(), ActorType::OBJ);
+        push_actors!(layer_data.objs(), ActorType::OBJS);
+        push_actors!(layer_data.sobj(), ActorType::SOBJ, size);
+        push_actors!(layer_data.sobs(), ActorType::SOBS, size);
+        push_actors!(layer_data.stag(), ActorType::STAG, size);
+        push_actors!(layer_data.stas(), ActorType::STAS, size);
+        push_actors!(layer_data.door(), ActorType::DOOR);
+    }
+
+    /// Draws only the actors that pass `filter`
+    pub fn draw_actors(&mut self, gl: &glow::Context, filter: impl Fn(&Actor) -> bool) {
+        if !self.render {
+            return;
+        }
+
+        for (i, actor) in self.actors.iter().enumerate() {
+            if filter(actor) {
+                self.mesh.draw_range(gl, i * BOX_VERTS, BOX_VERTS);
+            }
+        }
+    }
+}
+
+impl Model for ActorModel {
+    fn setup_gl(&mut self, gl: &glow::Context) {
+        self.mesh.setup_gl(gl);
+    }
+
+    fn destroy_gl(&mut self, gl: &glow::Context) {
+        self.mesh.destroy_gl(gl);
+    }
+
+    fn update_gl(&mut self, gl: &glow::Context) {
+        self.mesh.update_gl(gl);
+    }
+
+    fn draw(&mut self, gl: &glow::Context, _shader: &Shader) {
+        self.draw_actors(gl, |_| true);
+    }
+}
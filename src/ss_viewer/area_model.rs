@@ -0,0 +1,144 @@
+use crate::{
+    file_formats::BZS,
+    gfx::{
+        mesh::{push_box, BOX_VERTS},
+        Mesh, Model, Shader,
+    },
+};
+use eframe::glow;
+use glam::{Mat4, Vec3, Vec4};
+
+const AREA_COLOR: Vec4 = Vec4::new(0.2, 0.8, 0.9, 0.2);
+const HIGHLIGHT_COLOR: Vec4 = Vec4::new(1.0, 0.85, 0.2, 0.45);
+
+/// A box shaped volume (AREA) of a stage or room
+#[derive(Debug, Clone)]
+pub struct Area {
+    pub index: usize, // Index in the AREA list of its bzs
+    pub pos: Vec3,
+    pub size: Vec3,
+    pub angle: u16,
+    pub area_link: i16, // Index of the next linked area. -1 when not linked
+}
+
+impl Area {
+    /// The box is centered on `pos`, `size` is the full extent and `angle` rotates around Y
+    pub fn transform(&self) -> Mat4 {
+        Mat4::from_translation(self.pos)
+            * Mat4::from_rotation_y(self.angle as f32 * std::f32::consts::TAU / 65536.0)
+            * Mat4::from_scale(self.size)
+    }
+
+    pub fn label(&self) -> String {
+        match self.area_link {
+            -1 => format!("AREA {}", self.index),
+            link => format!("AREA {} -> {link}", self.index),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AreaModel {
+    pub name: String,
+    pub areas: Vec<Area>,
+
+    // Rendering Information
+    pub render: bool,
+    pub highlighted: Vec<bool>,
+    // The areas are stored twice, first with the normal color and then with the highlight color
+    mesh: Mesh,
+}
+
+impl AreaModel {
+    pub fn from_bzs(name: String, bzs: &BZS) -> Self {
+        let areas: Vec<Area> = bzs
+            .area()
+            .iter()
+            .enumerate()
+            .map(|(index, area)| Area {
+                index,
+                pos: Vec3::from_array(area.pos),
+                size: Vec3::from_array(area.size),
+                angle: area.angle,
+                area_link: area.area_link,
+            })
+            .collect();
+
+        let mut verts = Vec::with_capacity(areas.len() * BOX_VERTS * 2);
+        for clr in [AREA_COLOR, HIGHLIGHT_COLOR] {
+            areas
+                .iter()
+                .for_each(|area| push_box(&mut verts, &area.transform(), |_| clr));
+        }
+
+        Self {
+            name,
+            highlighted: vec![false; areas.len()],
+            areas,
+            render: true,
+            mesh: Mesh::new(verts, glow::TRIANGLES),
+        }
+    }
+
+    /// Every area that is connected to `index` through `area_link`, in either direction
+    pub fn linked_areas(&self, index: usize) -> Vec<usize> {
+        let mut linked = vec![index];
+        let mut i = 0;
+        while i < linked.len() {
+            let current = linked[i];
+            for area in &self.areas {
+                let link = area.area_link;
+                let neighbour = if area.index == current && link >= 0 {
+                    Some(link as usize)
+                } else if link >= 0 && link as usize == current {
+                    Some(area.index)
+                } else {
+                    None
+                };
+                if let Some(neighbour) = neighbour.filter(|n| *n < self.areas.len()) {
+                    if !linked.contains(&neighbour) {
+                        linked.push(neighbour);
+                    }
+                }
+            }
+            i += 1;
+        }
+        linked
+    }
+
+    /// Highlights the area at `index` and every area linked to it. None clears the highlight
+    pub fn highlight(&mut self, index: Option<usize>) {
+        self.highlighted.iter_mut().for_each(|h| *h = false);
+        if let Some(index) = index {
+            for linked in self.linked_areas(index) {
+                self.highlighted[linked] = true;
+            }
+        }
+    }
+}
+
+impl Model for AreaModel {
+    fn setup_gl(&mut self, gl: &glow::Context) {
+        self.mesh.setup_gl(gl);
+    }
+
+    fn destroy_gl(&mut self, gl: &glow::Context) {
+        self.mesh.destroy_gl(gl);
+    }
+
+    fn update_gl(&mut self, gl: &glow::Context) {
+        self.mesh.update_gl(gl);
+    }
+
+    fn draw(&mut self, gl: &glow::Context, _shader: &Shader) {
+        if !self.render {
+            return;
+        }
+
+        let highlight_start = self.areas.len() * BOX_VERTS;
+        for (i, &highlighted) in self.highlighted.iter().enumerate() {
+            let start = if highlighted { highlight_start } else { 0 };
+            self.mesh.draw_range(gl, start + i * BOX_VERTS, BOX_VERTS);
+        }
+    }
+}
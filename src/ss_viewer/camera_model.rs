@@ -0,0 +1,212 @@
+use crate::{
+    file_formats::BZS,
+    gfx::{Mesh, Model, Shader, Vertex},
+};
+use eframe::glow;
+use glam::{Mat4, Vec3, Vec4};
+
+const CAM_COLOR: Vec4 = Vec4::new(0.95, 0.95, 0.95, 1.0);
+const PCAM_COLOR: Vec4 = Vec4::new(0.4, 0.8, 1.0, 1.0);
+// Size of the frustum icon, from the eye to the near rectangle
+const FRUSTUM_LENGTH: f32 = 120.0;
+const FRUSTUM_WIDTH: f32 = 80.0;
+const FRUSTUM_HEIGHT: f32 = 50.0;
+// Half size of the cross marking a PCAM point
+const POINT_SIZE: f32 = 25.0;
+
+/// A scripted camera (CAM) of a stage or room
+#[derive(Debug, Clone)]
+pub struct StageCamera {
+    pub index: usize, // Index in the CAM list of its bzs
+    pub name: String,
+    pub pos: Vec3,
+    pub angle: f32,
+    pub unk1: [u8; 4],
+    pub unk2: [u8; 8],
+}
+
+impl StageCamera {
+    /// `angle` is assumed to be a rotation around Y in degrees, 0 looking towards +Z.
+    /// Nothing confirms this yet, so the UI labels it as an assumption
+    pub fn forward(&self) -> Vec3 {
+        let angle = self.angle.to_radians();
+        Vec3::new(angle.sin(), 0.0, angle.cos())
+    }
+
+    pub fn label(&self) -> String {
+        format!("CAM {}: {}", self.index, self.name)
+    }
+
+    pub fn tooltip(&self) -> String {
+        format!(
+            "{}\npos: ({:.1}, {:.1}, {:.1})\nangle: {:.2} (assumed yaw in degrees)\nunk1: {:02X?}\nunk2: {:02X?}",
+            self.label(),
+            self.pos.x,
+            self.pos.y,
+            self.pos.z,
+            self.angle,
+            self.unk1,
+            self.unk2,
+        )
+    }
+
+    fn build_verts(&self, verts: &mut Vec<Vertex>) {
+        let mtx = Mat4::from_translation(self.pos) * Mat4::from_rotation_y(self.angle.to_radians());
+        let (w, h) = (FRUSTUM_WIDTH / 2.0, FRUSTUM_HEIGHT / 2.0);
+        let corners = [
+            Vec3::new(-w, -h, FRUSTUM_LENGTH),
+            Vec3::new(w, -h, FRUSTUM_LENGTH),
+            Vec3::new(w, h, FRUSTUM_LENGTH),
+            Vec3::new(-w, h, FRUSTUM_LENGTH),
+        ];
+        let mut lines = Vec::new();
+        for i in 0..corners.len() {
+            lines.push([Vec3::ZERO, corners[i]]);
+            lines.push([corners[i], corners[(i + 1) % corners.len()]]);
+        }
+        // Triangle on top of the rectangle to show which way is up
+        let top = Vec3::new(0.0, h * 2.0, FRUSTUM_LENGTH);
+        lines.push([corners[2], top]);
+        lines.push([top, corners[3]]);
+
+        for line in lines {
+            push_line(
+                verts,
+                mtx.transform_point3(line[0]),
+                mtx.transform_point3(line[1]),
+                CAM_COLOR,
+            );
+        }
+    }
+}
+
+/// A camera (PCAM) given by two points, assumed to be the eye and the target
+#[derive(Debug, Clone)]
+pub struct PointCamera {
+    pub index: usize, // Index in the PCAM list of its bzs
+    pub pos1: Vec3,
+    pub pos2: Vec3,
+    pub angle: f32,
+    pub unkf: f32,
+    pub unk: [u8; 4],
+}
+
+impl PointCamera {
+    pub fn label(&self) -> String {
+        format!("PCAM {}", self.index)
+    }
+
+    pub fn tooltip(&self) -> String {
+        format!(
+            "{}\npos1: ({:.1}, {:.1}, {:.1}) (assumed eye)\npos2: ({:.1}, {:.1}, {:.1}) (assumed target)\nangle: {:.2}\nunkf: {:.2}\nunk: {:02X?}",
+            self.label(),
+            self.pos1.x,
+            self.pos1.y,
+            self.pos1.z,
+            self.pos2.x,
+            self.pos2.y,
+            self.pos2.z,
+            self.angle,
+            self.unkf,
+            self.unk,
+        )
+    }
+
+    fn build_verts(&self, verts: &mut Vec<Vertex>) {
+        push_line(verts, self.pos1, self.pos2, PCAM_COLOR);
+        for pos in [self.pos1, self.pos2] {
+            for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                let offset = axis * POINT_SIZE;
+                push_line(verts, pos - offset, pos + offset, PCAM_COLOR);
+            }
+        }
+    }
+}
+
+fn push_line(verts: &mut Vec<Vertex>, start: Vec3, end: Vec3, clr: Vec4) {
+    let nrm = (end - start).normalize_or(Vec3::Y);
+    verts.push(Vertex::new(start, nrm, clr));
+    verts.push(Vertex::new(end, nrm, clr));
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraModel {
+    pub name: String,
+    pub cameras: Vec<StageCamera>,
+    pub point_cameras: Vec<PointCamera>,
+
+    // Rendering Information
+    pub render: bool,
+    mesh: Mesh,
+}
+
+impl CameraModel {
+    pub fn from_bzs(name: String, bzs: &BZS) -> Self {
+        let cameras: Vec<StageCamera> = bzs
+            .cam()
+            .iter()
+            .enumerate()
+            .map(|(index, cam)| StageCamera {
+                index,
+                name: cam.name.as_str().to_string(),
+                pos: Vec3::from_array(cam.pos),
+                angle: cam.angle,
+                unk1: cam.unk1,
+                unk2: cam.unk2,
+            })
+            .collect();
+        let point_cameras: Vec<PointCamera> = bzs
+            .pcam()
+            .iter()
+            .enumerate()
+            .map(|(index, pcam)| PointCamera {
+                index,
+                pos1: Vec3::from_array(pcam.pos1),
+                pos2: Vec3::from_array(pcam.pos2),
+                angle: pcam.angle,
+                unkf: pcam.unkf,
+                unk: pcam.unk,
+            })
+            .collect();
+
+        let mut verts = Vec::new();
+        cameras.iter().for_each(|cam| cam.build_verts(&mut verts));
+        point_cameras
+            .iter()
+            .for_each(|pcam| pcam.build_verts(&mut verts));
+
+        Self {
+            name,
+            cameras,
+            point_cameras,
+            render: true,
+            mesh: Mesh::new(verts, glow::LINES),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cameras.is_empty() && self.point_cameras.is_empty()
+    }
+}
+
+impl Model for CameraModel {
+    fn setup_gl(&mut self, gl: &glow::Context) {
+        self.mesh.setup_gl(gl);
+    }
+
+    fn destroy_gl(&mut self, gl: &glow::Context) {
+        self.mesh.destroy_gl(gl);
+    }
+
+    fn update_gl(&mut self, gl: &glow::Context) {
+        self.mesh.update_gl(gl);
+    }
+
+    fn draw(&mut self, gl: &glow::Context, shader: &Shader) {
+        if !self.render {
+            return;
+        }
+
+        self.mesh.draw(gl, shader);
+    }
+}
@@ -3,11 +3,86 @@ use std::{
 };
 
 use crate::{
-    file_formats::{PLCEntry, DZB, PLC},
+    file_formats::{dzb::Group, PLCEntry, DZB, PLC},
     gfx::{Model, Shader, Vertex},
 };
 use eframe::glow;
-use glam::{Vec3, Vec3Swizzles};
+use glam::{Mat4, Vec3Swizzles};
+
+// Used for missing parents/children in groups
+const NO_GROUP: u16 = 0xFFFF;
+
+/// A named part of the collision. Groups form a hierarchy and each one has its own transform
+#[derive(Debug, Clone)]
+pub struct DZBGroup {
+    pub name: String,
+    pub room_id: u16,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub transform: Mat4, // Composed with the transforms of all the parents
+    pub render: bool,
+    // Vertices of the triangles of the group in the model
+    first_vert: usize,
+    vert_count: usize,
+}
+
+impl DZBGroup {
+    // Scale, then rotation, then translation. Angles are 16 bit and applied X, then Y, then Z (Rz * Ry * Rx) like the
+    //  J3D joints of Wind Waker, where DZB comes from (J3DGetTranslateRotateMtx). Actors use Y * X * Z instead (ZXYrotM).
+    //  Neither the order nor the vertices being relative to their group is confirmed for SS, see `group_transforms`
+    fn local_transform(group: &Group) -> Mat4 {
+        let [x, y, z] = group
+            .rotation
+            .map(|a| a as f32 * std::f32::consts::TAU / 65536.0);
+        // A scale of 0 would hide the whole group
+        let scale = group.scale.map(|s| if s == 0.0 { 1.0 } else { s });
+        Mat4::from_translation(group.translation)
+            * Mat4::from_rotation_z(z)
+            * Mat4::from_rotation_y(y)
+            * Mat4::from_rotation_x(x)
+            * Mat4::from_scale(scale)
+    }
+
+    // Composes the transforms from the root groups down
+    fn build_groups(groups: &[Group]) -> Vec<Self> {
+        let index = |idx: u16| Some(idx as usize).filter(|&i| idx != NO_GROUP && i < groups.len());
+
+        let mut out: Vec<Self> = groups
+            .iter()
+            .map(|group| Self {
+                name: group.name.clone(),
+                room_id: group.room_id,
+                parent: index(group.parent_group_idx),
+                children: Vec::new(),
+                transform: Self::local_transform(group),
+                render: true,
+                first_vert: 0,
+                vert_count: 0,
+            })
+            .collect();
+        for i in 0..out.len() {
+            if let Some(parent) = out[i].parent {
+                out[parent].children.push(i);
+            }
+        }
+
+        // Parents are visited before their children. Groups in a loop keep their local transform
+        let mut stack: Vec<usize> = (0..out.len())
+            .filter(|&i| out[i].parent.is_none())
+            .collect();
+        let mut visited = vec![false; out.len()];
+        while let Some(i) = stack.pop() {
+            if std::mem::replace(&mut visited[i], true) {
+                continue;
+            }
+            for child in out[i].children.clone() {
+                out[child].transform = out[i].transform * out[child].transform;
+                stack.push(child);
+            }
+        }
+        out
+    }
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -19,9 +94,14 @@ pub struct DZBModel {
     // Properties of each vertex -> prop_i -> (vtx_3i, vtx_3i+1, vtx_3i+2)
     pub properties: Vec<PLCEntry>,
 
+    pub groups: Vec<DZBGroup>,
+    // Vertices are assumed to be relative to their group. Can be turned off in case they are already in world space
+    pub group_transforms: bool,
+
     // Rendering Information
     pub render: bool,
     pub verts: Vec<Vertex>,
+    verts_changed: bool, // The vertex buffer needs to be uploaded again
     vao: Option<glow::VertexArray>,
     vbo: Option<glow::Buffer>,
 }
@@ -55,48 +135,91 @@ impl DZBModel {
             .expect("Could not make String from OsString");
         println!("{}: Creating DZB Model", dzb_path.display());
 
+        Self::from_data(name, &fs::read(dzb_path)?, &fs::read(plc_path)?)
+    }
+
+    pub fn from_data(
+        name: String,
+        dzb_data: &[u8],
+        plc_data: &[u8],
+    ) -> Result<Self, Box<dyn Error>> {
         // Read The PLC and the DZB File
-        let dzb = DZB::from_file(&mut Cursor::new(&fs::read(dzb_path)?))?;
-        let plc = PLC::from_file(&mut Cursor::new(&fs::read(plc_path)?))?;
+        let dzb = DZB::from_file(&mut Cursor::new(dzb_data))?;
+        let plc = PLC::from_file(&mut Cursor::new(plc_data))?;
 
         // The Important things for the model are vertices to draw
-        // The DZB Stores Triangles directly, so no need to extract them manually
-        let tris = &dzb.tris;
+        // The DZB Stores Triangles directly, so no need to extract them manually.
+        //  They are sorted by group so each group can be drawn on its own
+        let mut groups = DZBGroup::build_groups(&dzb.groups);
+        let mut tris: Vec<_> = dzb.tris.iter().collect();
+        tris.sort_by_key(|tri| tri.group_idx);
 
         // Since the the plc file is index'd into and we may want to edit a single triangle,
         //  each property needs to be duplicated. On rebuilding, it should perform de-duplication
-        let mut vtx_array =
-            Vec::<Vertex>::with_capacity(tris.len() * 3 /* Three Verts per Tri */);
         let mut prop_array =
             Vec::<PLCEntry>::with_capacity(tris.len() /* One Property per Tri */);
-        tris.iter().for_each(|tri| {
-            let verts: Vec<Vec3> = tri
-                .vert_idx
-                .iter()
-                .map(|&idx| dzb.verts[idx as usize])
-                .collect();
-
-            let nrm = (verts[1] - verts[0]).cross(verts[2] - verts[0]).normalize();
-            let clr = nrm.abs().xyzx().with_w(1.0);
-
-            verts
-                .iter()
-                .for_each(|vtx| vtx_array.push(Vertex::new(vtx.clone(), nrm, clr)));
+        tris.iter().enumerate().for_each(|(i, tri)| {
+            if let Some(group) = groups.get_mut(tri.group_idx as usize) {
+                if group.vert_count == 0 {
+                    group.first_vert = i * 3;
+                }
+                group.vert_count += 3;
+            }
             prop_array.push(plc.entries[tri.prop_idx as usize].clone());
         });
 
-        let model = Self {
+        let mut model = Self {
             name,
             file: dzb,
+            groups,
+            group_transforms: true,
             render: true,
-            verts: vtx_array,
+            verts: Vec::new(),
+            verts_changed: false,
             properties: prop_array,
             vao: None,
             vbo: None,
         };
+        model.build_verts();
 
         Ok(model)
     }
+
+    // Places the triangles in the same order as `from_data`, with or without the transforms of their groups.
+    //  The colors are kept when the vertices are built again
+    fn build_verts(&mut self) {
+        let mut tris: Vec<_> = self.file.tris.iter().collect();
+        tris.sort_by_key(|tri| tri.group_idx);
+
+        let mut vtx_array =
+            Vec::<Vertex>::with_capacity(tris.len() * 3 /* Three Verts per Tri */);
+        tris.iter().for_each(|tri| {
+            let transform = self
+                .groups
+                .get(tri.group_idx as usize)
+                .filter(|_| self.group_transforms)
+                .map_or(Mat4::IDENTITY, |group| group.transform);
+
+            let verts = tri
+                .vert_idx
+                .map(|idx| transform.transform_point3(self.file.verts[idx as usize]));
+
+            let nrm = (verts[1] - verts[0]).cross(verts[2] - verts[0]).normalize();
+            let clr = nrm.abs().xyzx().with_w(1.0);
+
+            verts
+                .iter()
+                .for_each(|vtx| vtx_array.push(Vertex::new(*vtx, nrm, clr)));
+        });
+
+        if self.verts.len() == vtx_array.len() {
+            vtx_array
+                .iter_mut()
+                .zip(&self.verts)
+                .for_each(|(vtx, old)| vtx.clr = old.clr);
+        }
+        self.verts = vtx_array;
+    }
 }
 
 impl Model for DZBModel {
@@ -201,11 +324,195 @@ impl Model for DZBModel {
             return;
         }
 
+        if std::mem::take(&mut self.verts_changed) {
+            self.update_gl(gl);
+        }
+
         unsafe {
             use glow::HasContext as _;
 
             gl.bind_vertex_array(self.vao);
-            gl.draw_arrays(glow::TRIANGLES, 0, self.verts.len() as _);
+            if self.groups.iter().all(|group| group.render) {
+                gl.draw_arrays(glow::TRIANGLES, 0, self.verts.len() as _);
+                return;
+            }
+
+            // Triangles of missing groups are always drawn, they come after the ones of the groups
+            let grouped: usize = self.groups.iter().map(|group| group.vert_count).sum();
+            gl.draw_arrays(
+                glow::TRIANGLES,
+                grouped as _,
+                (self.verts.len() - grouped) as _,
+            );
+            for (i, group) in self.groups.iter().enumerate() {
+                if group.vert_count > 0 && self.group_visible(i) {
+                    gl.draw_arrays(
+                        glow::TRIANGLES,
+                        group.first_vert as _,
+                        group.vert_count as _,
+                    );
+                }
+            }
         }
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                                 Group Hierarchy                                                   //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl DZBModel {
+    // A group is only drawn while it and all its parents are enabled
+    fn group_visible(&self, mut index: usize) -> bool {
+        for _ in 0..self.groups.len() {
+            if !self.groups[index].render {
+                return false;
+            }
+            match self.groups[index].parent {
+                Some(parent) => index = parent,
+                None => break,
+            }
+        }
+        true
+    }
+
+    /// Tree of the groups with a checkbox for each of them
+    pub fn groups_ui(&mut self, ui: &mut egui::Ui) {
+        let roots: Vec<usize> = (0..self.groups.len())
+            .filter(|&i| self.groups[i].parent.is_none())
+            .collect();
+        let id = ui.make_persistent_id((&self.name, "groups"));
+        egui::CollapsingHeader::new(format!("Groups ({})", self.groups.len()))
+            .id_source(id)
+            .show(ui, |ui| {
+                if ui
+                    .checkbox(&mut self.group_transforms, "Apply Group Transforms")
+                    .on_hover_text(
+                        "Assumes the vertices are relative to their group. \
+                         Turn off if the collision is out of place",
+                    )
+                    .changed()
+                {
+                    self.build_verts();
+                    self.verts_changed = true;
+                }
+                let mut depth_left = self.groups.len();
+                roots
+                    .into_iter()
+                    .for_each(|root| self.group_ui(ui, root, &mut depth_left));
+            });
+    }
+
+    // `budget` stops groups that are their own parents from recursing forever
+    fn group_ui(&mut self, ui: &mut egui::Ui, index: usize, budget: &mut usize) {
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+
+        let group = &mut self.groups[index];
+        let label = format!("{} (room {})", group.name, group.room_id);
+        if group.children.is_empty() {
+            ui.checkbox(&mut group.render, label);
+            return;
+        }
+
+        let children = group.children.clone();
+        let id = ui.make_persistent_id((&self.name, "group", index));
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+            .show_header(ui, |ui| {
+                ui.checkbox(&mut self.groups[index].render, label);
+            })
+            .body(|ui| {
+                children
+                    .into_iter()
+                    .for_each(|child| self.group_ui(ui, child, budget));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    const QUARTER_TURN: i16 = 0x4000;
+
+    fn group(parent_group_idx: u16, rotation: [i16; 3], translation: Vec3) -> Group {
+        Group {
+            name: String::new(),
+            name_offset: 0,
+            scale: Vec3::ONE,
+            rotation,
+            unk1: 0,
+            translation,
+            parent_group_idx,
+            next_sibling_group: NO_GROUP,
+            first_child_group_index: NO_GROUP,
+            room_id: 0,
+            first_vtx_idx: 0,
+            tree_index: 0,
+            info: 0,
+        }
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn rotation_is_x_then_y_then_z() {
+        let groups =
+            DZBGroup::build_groups(&[group(NO_GROUP, [QUARTER_TURN, QUARTER_TURN, 0], Vec3::ZERO)]);
+        // X turns +Y to +Z, then Y turns +Z to +X
+        assert_near(groups[0].transform.transform_point3(Vec3::Y), Vec3::X);
+    }
+
+    #[test]
+    fn children_compose_with_their_parents() {
+        let groups = DZBGroup::build_groups(&[
+            group(NO_GROUP, [0, QUARTER_TURN, 0], Vec3::new(10.0, 0.0, 0.0)),
+            group(0, [0; 3], Vec3::new(0.0, 0.0, 5.0)),
+            group(1, [0; 3], Vec3::new(0.0, 2.0, 0.0)),
+        ]);
+        assert_eq!(groups[0].children, [1]);
+        assert_eq!(groups[2].parent, Some(1));
+
+        // The child offset is turned by the rotation of the parent
+        assert_near(
+            groups[1].transform.transform_point3(Vec3::ZERO),
+            Vec3::new(15.0, 0.0, 0.0),
+        );
+        assert_near(
+            groups[2].transform.transform_point3(Vec3::ZERO),
+            Vec3::new(15.0, 2.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn missing_parents_make_root_groups() {
+        let offset = Vec3::new(1.0, 2.0, 3.0);
+        let groups = DZBGroup::build_groups(&[
+            group(NO_GROUP, [0; 3], Vec3::new(100.0, 0.0, 0.0)),
+            group(7, [0; 3], offset), // Out of range
+            group(NO_GROUP, [0; 3], offset),
+        ]);
+        assert!(groups.iter().all(|group| group.parent.is_none()));
+        assert!(groups.iter().all(|group| group.children.is_empty()));
+        assert_near(groups[1].transform.transform_point3(Vec3::ZERO), offset);
+    }
+
+    #[test]
+    fn groups_in_a_loop_keep_their_local_transform() {
+        let groups = DZBGroup::build_groups(&[
+            group(1, [0; 3], Vec3::X),
+            group(0, [0; 3], Vec3::Y),
+            group(2, [0; 3], Vec3::Z), // Its own parent
+            group(NO_GROUP, [0; 3], Vec3::ONE),
+        ]);
+        assert_near(groups[0].transform.transform_point3(Vec3::ZERO), Vec3::X);
+        assert_near(groups[1].transform.transform_point3(Vec3::ZERO), Vec3::Y);
+        assert_near(groups[2].transform.transform_point3(Vec3::ZERO), Vec3::Z);
+        assert_near(groups[3].transform.transform_point3(Vec3::ZERO), Vec3::ONE);
+    }
+}
@@ -0,0 +1,183 @@
+use std::{error::Error, fmt::Write as _, fs, path::Path};
+
+use crate::file_formats::bzs::SCEN;
+
+/// An exit (SCEN) leading out of a stage or room
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageExit {
+    pub stage: String,  // Stage containing the exit
+    pub source: String, // Name of the node (stage or room) the exit belongs to
+    pub index: usize,   // Index in the SCEN list of its bzs
+    pub target_stage: String,
+    pub room: u8,
+    pub layer: u8,
+    pub entrance: u8,
+    pub night: u8,
+    pub byte5: u8,
+    pub flag6: u8,
+    pub save_prompt: u8,
+}
+
+impl StageExit {
+    pub fn new(stage: String, source: String, index: usize, scen: &SCEN) -> Self {
+        Self {
+            stage,
+            source,
+            index,
+            target_stage: scen.name.as_str().to_string(),
+            room: scen.room,
+            layer: scen.layer,
+            entrance: scen.entrance,
+            night: scen.night,
+            byte5: scen.byte5,
+            flag6: scen.flag6,
+            save_prompt: scen.save_prompt,
+        }
+    }
+}
+
+/// The connections between every loaded stage, built from their exits
+#[derive(Debug, Clone, Default)]
+pub struct EntranceGraph {
+    pub exits: Vec<StageExit>,
+}
+
+impl EntranceGraph {
+    pub fn new(exits: Vec<StageExit>) -> Self {
+        Self { exits }
+    }
+
+    /// Every stage that is the source or the target of an exit, sorted by name
+    pub fn stages(&self) -> Vec<&str> {
+        let mut stages: Vec<&str> = self
+            .exits
+            .iter()
+            .flat_map(|exit| [exit.stage.as_str(), exit.target_stage.as_str()])
+            .collect();
+        stages.sort_unstable();
+        stages.dedup();
+        stages
+    }
+
+    /// Graphviz graph with a node per stage and an edge per exit
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph entrances {\n");
+        for stage in self.stages() {
+            writeln!(dot, "    {};", quote(stage)).unwrap();
+        }
+        for exit in &self.exits {
+            let night = if exit.night != 0 { ", night" } else { "" };
+            let label = format!(
+                "{} #{} -> room {}, layer {}, entrance {}{night}",
+                exit.source, exit.index, exit.room, exit.layer, exit.entrance
+            );
+            writeln!(
+                dot,
+                "    {} -> {} [label={}];",
+                quote(&exit.stage),
+                quote(&exit.target_stage),
+                quote(&label)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// `{"stages": [...], "exits": [{...}, ...]}`
+    pub fn to_json(&self) -> String {
+        let stages: Vec<String> = self.stages().into_iter().map(quote).collect();
+        let exits: Vec<String> = self
+            .exits
+            .iter()
+            .map(|exit| {
+                format!(
+                    "    {{\"stage\": {}, \"source\": {}, \"index\": {}, \"target_stage\": {}, \"room\": {}, \"layer\": {}, \"entrance\": {}, \"night\": {}, \"byte5\": {}, \"flag6\": {}, \"save_prompt\": {}}}",
+                    quote(&exit.stage),
+                    quote(&exit.source),
+                    exit.index,
+                    quote(&exit.target_stage),
+                    exit.room,
+                    exit.layer,
+                    exit.entrance,
+                    exit.night,
+                    exit.byte5,
+                    exit.flag6,
+                    exit.save_prompt,
+                )
+            })
+            .collect();
+        format!(
+            "{{\n  \"stages\": [{}],\n  \"exits\": [\n{}\n  ]\n}}\n",
+            stages.join(", "),
+            exits.join(",\n")
+        )
+    }
+
+    /// Writes `entrance_graph.dot` and `entrance_graph.json` into `dir`
+    pub fn export(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(dir.join("entrance_graph.dot"), self.to_dot())?;
+        fs::write(dir.join("entrance_graph.json"), self.to_json())?;
+        Ok(())
+    }
+}
+
+// Quoted string that is valid in both DOT and JSON
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit(stage: &str, target_stage: &str, entrance: u8) -> StageExit {
+        StageExit {
+            stage: stage.to_string(),
+            source: "Room 0".to_string(),
+            index: 0,
+            target_stage: target_stage.to_string(),
+            room: 1,
+            layer: 2,
+            entrance,
+            night: 0,
+            byte5: 0,
+            flag6: 0,
+            save_prompt: 0,
+        }
+    }
+
+    #[test]
+    fn exports_every_stage_and_exit() {
+        let graph = EntranceGraph::new(vec![exit("F000", "F001r", 3), exit("F001r", "F000", 4)]);
+        assert_eq!(graph.stages(), ["F000", "F001r"]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains(
+            "\"F000\" -> \"F001r\" [label=\"Room 0 #0 -> room 1, layer 2, entrance 3\"];"
+        ));
+        assert!(dot.contains("\"F001r\" -> \"F000\""));
+
+        let json = graph.to_json();
+        assert!(json.contains("\"stages\": [\"F000\", \"F001r\"]"));
+        assert!(json
+            .contains("\"target_stage\": \"F001r\", \"room\": 1, \"layer\": 2, \"entrance\": 3"));
+    }
+
+    #[test]
+    fn quotes_special_characters() {
+        assert_eq!(quote("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
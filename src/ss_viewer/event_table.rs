@@ -0,0 +1,268 @@
+use egui_extras::{Column, TableBuilder};
+
+use crate::file_formats::bzs::EVNT;
+
+/// Story, scene and skip flags are separate, the same number means a different flag in each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagKind {
+    Story,
+    Scene,
+    Skip,
+}
+
+impl FlagKind {
+    const ALL: [Self; 3] = [Self::Story, Self::Scene, Self::Skip];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Story => "Story Flag",
+            Self::Scene => "Scene Flag",
+            Self::Skip => "Skip Flag",
+        }
+    }
+}
+
+/// An event (EVNT) of a stage or room
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub source: String, // Name of the node (stage or room) the event belongs to
+    pub index: usize,   // Index in the EVNT list of its bzs
+    pub name: String,
+    pub story_flag1: i16,
+    pub story_flag2: i16,
+    pub sceneflag1: u8,
+    pub sceneflag2: u8,
+    pub skipflag: u8,
+    pub exit_id: u8,
+    pub item: i16,
+}
+
+impl Event {
+    pub fn new(source: String, index: usize, evnt: &EVNT) -> Self {
+        Self {
+            source,
+            index,
+            name: evnt.name.as_str().to_string(),
+            story_flag1: evnt.story_flag1,
+            story_flag2: evnt.story_flag2,
+            sceneflag1: evnt.sceneflag1,
+            sceneflag2: evnt.sceneflag2,
+            skipflag: evnt.skipflag,
+            exit_id: evnt.exit_id,
+            item: evnt.item,
+        }
+    }
+
+    /// The event reads or sets the flag `flag` of the kind `kind`
+    pub fn uses_flag(&self, kind: FlagKind, flag: i32) -> bool {
+        match kind {
+            FlagKind::Story => [self.story_flag1, self.story_flag2]
+                .map(i32::from)
+                .contains(&flag),
+            FlagKind::Scene => [self.sceneflag1, self.sceneflag2]
+                .map(i32::from)
+                .contains(&flag),
+            FlagKind::Skip => i32::from(self.skipflag) == flag,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventColumn {
+    Source,
+    Name,
+    StoryFlag1,
+    StoryFlag2,
+    SceneFlag1,
+    SceneFlag2,
+    SkipFlag,
+    ExitId,
+    Item,
+}
+
+impl EventColumn {
+    const ALL: [Self; 9] = [
+        Self::Source,
+        Self::Name,
+        Self::StoryFlag1,
+        Self::StoryFlag2,
+        Self::SceneFlag1,
+        Self::SceneFlag2,
+        Self::SkipFlag,
+        Self::ExitId,
+        Self::Item,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Source => "Source",
+            Self::Name => "Name",
+            Self::StoryFlag1 => "Story 1",
+            Self::StoryFlag2 => "Story 2",
+            Self::SceneFlag1 => "Scene 1",
+            Self::SceneFlag2 => "Scene 2",
+            Self::SkipFlag => "Skip",
+            Self::ExitId => "Exit",
+            Self::Item => "Item",
+        }
+    }
+
+    fn text(&self, event: &Event) -> String {
+        match self {
+            Self::Source => format!("{} #{}", event.source, event.index),
+            Self::Name => event.name.clone(),
+            Self::StoryFlag1 => event.story_flag1.to_string(),
+            Self::StoryFlag2 => event.story_flag2.to_string(),
+            Self::SceneFlag1 => event.sceneflag1.to_string(),
+            Self::SceneFlag2 => event.sceneflag2.to_string(),
+            Self::SkipFlag => event.skipflag.to_string(),
+            Self::ExitId => event.exit_id.to_string(),
+            Self::Item => event.item.to_string(),
+        }
+    }
+
+    fn compare(&self, a: &Event, b: &Event) -> std::cmp::Ordering {
+        match self {
+            Self::Source => (&a.source, a.index).cmp(&(&b.source, b.index)),
+            Self::Name => a.name.cmp(&b.name),
+            Self::StoryFlag1 => a.story_flag1.cmp(&b.story_flag1),
+            Self::StoryFlag2 => a.story_flag2.cmp(&b.story_flag2),
+            Self::SceneFlag1 => a.sceneflag1.cmp(&b.sceneflag1),
+            Self::SceneFlag2 => a.sceneflag2.cmp(&b.sceneflag2),
+            Self::SkipFlag => a.skipflag.cmp(&b.skipflag),
+            Self::ExitId => a.exit_id.cmp(&b.exit_id),
+            Self::Item => a.item.cmp(&b.item),
+        }
+    }
+}
+
+/// Sorting and filtering state of the event browser
+#[derive(Debug, Clone)]
+pub struct EventTable {
+    sort_column: EventColumn,
+    ascending: bool,
+    flag_kind: FlagKind,
+    flag_filter: String, // Flag number typed by the user, empty shows every event
+}
+
+impl EventTable {
+    pub fn new() -> Self {
+        Self {
+            sort_column: EventColumn::Source,
+            ascending: true,
+            flag_kind: FlagKind::Story,
+            flag_filter: String::new(),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, events: &[Event]) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("Event Flag Kind")
+                .selected_text(self.flag_kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in FlagKind::ALL {
+                        ui.selectable_value(&mut self.flag_kind, kind, kind.name());
+                    }
+                });
+            ui.text_edit_singleline(&mut self.flag_filter)
+                .on_hover_text("Only show the events using this flag");
+        });
+        let flag = self.flag_filter.trim().parse::<i32>().ok();
+
+        let mut rows: Vec<&Event> = events
+            .iter()
+            .filter(|event| flag.is_none_or(|flag| event.uses_flag(self.flag_kind, flag)))
+            .collect();
+        rows.sort_by(|a, b| {
+            let ordering = self.sort_column.compare(a, b);
+            if self.ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .max_scroll_height(300.0)
+            .columns(Column::auto().resizable(true), EventColumn::ALL.len())
+            .header(20.0, |mut header| {
+                for column in EventColumn::ALL {
+                    header.col(|ui| {
+                        let arrow = match (column == self.sort_column, self.ascending) {
+                            (true, true) => " ^",
+                            (true, false) => " v",
+                            (false, _) => "",
+                        };
+                        if ui.button(format!("{}{arrow}", column.name())).clicked() {
+                            // Clicking the sorted column again flips the order
+                            self.ascending = column != self.sort_column || !self.ascending;
+                            self.sort_column = column;
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, rows.len(), |mut row| {
+                    let event = rows[row.index()];
+                    for column in EventColumn::ALL {
+                        row.col(|ui| {
+                            ui.label(column.text(event));
+                        });
+                    }
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    fn event(source: &str, index: usize, name: &str, story_flag1: i16, sceneflag1: u8) -> Event {
+        Event {
+            source: source.to_string(),
+            index,
+            name: name.to_string(),
+            story_flag1,
+            story_flag2: -1,
+            sceneflag1,
+            sceneflag2: 0xFF,
+            skipflag: 5,
+            exit_id: 0,
+            item: -1,
+        }
+    }
+
+    #[test]
+    fn flags_are_matched_by_kind() {
+        let story = event("F000", 0, "A", 5, 0xFF);
+        let scene = event("F000", 1, "B", -1, 5);
+
+        assert!(story.uses_flag(FlagKind::Story, 5));
+        assert!(!story.uses_flag(FlagKind::Scene, 5));
+        assert!(scene.uses_flag(FlagKind::Scene, 5));
+        assert!(!scene.uses_flag(FlagKind::Story, 5));
+        assert!(scene.uses_flag(FlagKind::Story, -1));
+        assert!(story.uses_flag(FlagKind::Skip, 5));
+        assert!(!story.uses_flag(FlagKind::Skip, 6));
+    }
+
+    #[test]
+    fn columns_compare_their_values() {
+        let a = event("F000", 2, "Zelda", 10, 3);
+        let b = event("F000", 10, "Aqua", -1, 4);
+        let c = event("D100", 0, "Aqua", 3, 4);
+
+        // Sources are sorted by name, then by the index in their bzs
+        assert_eq!(EventColumn::Source.compare(&a, &b), Ordering::Less);
+        assert_eq!(EventColumn::Source.compare(&c, &a), Ordering::Less);
+        assert_eq!(EventColumn::Name.compare(&a, &b), Ordering::Greater);
+        assert_eq!(EventColumn::Name.compare(&b, &c), Ordering::Equal);
+        // Flags compare as numbers, -1 (no flag) first
+        assert_eq!(EventColumn::StoryFlag1.compare(&b, &c), Ordering::Less);
+        assert_eq!(EventColumn::StoryFlag1.compare(&a, &c), Ordering::Greater);
+        assert_eq!(EventColumn::SceneFlag1.compare(&a, &b), Ordering::Less);
+    }
+}
@@ -56,9 +56,17 @@ impl KCLModel {
             .expect("Could not make String from OsString");
         println!("{}: Creating KCL Model", kcl_path.display());
 
+        Self::from_data(name, &fs::read(kcl_path)?, &fs::read(plc_path)?)
+    }
+
+    pub fn from_data(
+        name: String,
+        kcl_data: &[u8],
+        plc_data: &[u8],
+    ) -> Result<Self, Box<dyn Error>> {
         // Read The PLC and the KCL File
-        let kcl = KCL::from_file(&mut Cursor::new(&fs::read(kcl_path)?))?;
-        let plc = PLC::from_file(&mut Cursor::new(&fs::read(plc_path)?))?;
+        let kcl = KCL::from_file(&mut Cursor::new(kcl_data))?;
+        let plc = PLC::from_file(&mut Cursor::new(plc_data))?;
 
         // The Important things for the model are vertices to draw
         // In KCL the position is stored and then the triangle needs calculation from the normals
@@ -75,7 +83,7 @@ impl KCLModel {
             let clr = tri.face_normal.abs().xyzx().with_w(1.0);
             tri.vertices
                 .iter()
-                .for_each(|vtx| vtx_array.push(Vertex::new(vtx.clone(), tri.face_normal, clr)));
+                .for_each(|vtx| vtx_array.push(Vertex::new(*vtx, tri.face_normal, clr)));
             prop_array.push(plc.entries[tri.attribute as usize].clone());
         });
 
@@ -1,7 +1,20 @@
+pub mod actor_model;
+pub mod area_model;
+pub mod camera_model;
 pub mod dzb_model;
+pub mod entrance_graph;
+pub mod event_table;
 pub mod kcl_model;
+pub mod path_model;
 pub mod plc;
+pub mod plc_filter;
 pub mod scene;
+pub mod spawn_model;
 
+pub use actor_model::ActorModel;
+pub use area_model::AreaModel;
+pub use camera_model::CameraModel;
 pub use dzb_model::DZBModel;
 pub use kcl_model::KCLModel;
+pub use path_model::PathModel;
+pub use spawn_model::SpawnModel;
@@ -0,0 +1,285 @@
+use crate::{
+    file_formats::BZS,
+    gfx::{Mesh, Model, Shader, Vertex},
+};
+use eframe::glow;
+use glam::{Vec3, Vec4};
+
+// Number of lines used to draw a single bezier segment
+const BEZIER_STEPS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathType {
+    PATH, // Through PNT
+    SPTH, // Through SPNT
+    BPNT, // Bezier curve through BPNT
+}
+
+impl PathType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PATH => "PATH",
+            Self::SPTH => "SPTH",
+            Self::BPNT => "BPNT",
+        }
+    }
+
+    fn color(&self) -> Vec4 {
+        match self {
+            Self::PATH => Vec4::new(1.0, 0.55, 0.1, 1.0),
+            Self::SPTH => Vec4::new(0.9, 0.3, 0.9, 1.0),
+            Self::BPNT => Vec4::new(0.3, 1.0, 0.5, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PathPoint {
+    pub index: usize, // Index in the point list (PNT/SPNT/BPNT) of the bzs
+    pub pos: Vec3,
+    // Bezier handles, only for BPNT
+    pub handles: Option<[Vec3; 2]>,
+    pub unk: [u8; 4],
+}
+
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub path_type: PathType,
+    pub index: usize, // Index in PATH/SPTH of the bzs
+    pub unk1: u8,
+    pub unk2: u8,
+    pub unk3: [u8; 6],
+    pub points: Vec<PathPoint>,
+}
+
+impl Path {
+    // The line segments of the path as pairs of points
+    fn lines(&self) -> Vec<[Vec3; 2]> {
+        let mut lines = Vec::new();
+        for pair in self.points.windows(2) {
+            let (start, end) = (&pair[0], &pair[1]);
+            match (start.handles, end.handles) {
+                // Cubic bezier from the point through its out handle and the in handle of the next one
+                (Some([_, out_handle]), Some([in_handle, _])) => {
+                    let curve = [start.pos, out_handle, in_handle, end.pos];
+                    let mut prev = start.pos;
+                    for step in 1..=BEZIER_STEPS {
+                        let next = cubic_bezier(&curve, step as f32 / BEZIER_STEPS as f32);
+                        lines.push([prev, next]);
+                        prev = next;
+                    }
+                }
+                _ => lines.push([start.pos, end.pos]),
+            }
+        }
+
+        // Show the handles of the bezier points
+        for point in &self.points {
+            if let Some(handles) = point.handles {
+                handles
+                    .iter()
+                    .for_each(|&handle| lines.push([point.pos, handle]));
+            }
+        }
+        lines
+    }
+}
+
+fn cubic_bezier(p: &[Vec3; 4], t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    p[0] * (u * u * u) + p[1] * (3.0 * u * u * t) + p[2] * (3.0 * u * t * t) + p[3] * (t * t * t)
+}
+
+#[derive(Debug, Clone)]
+pub struct PathModel {
+    pub name: String,
+    pub paths: Vec<Path>,
+
+    // Rendering Information
+    pub render: bool,
+    mesh: Mesh,
+}
+
+impl PathModel {
+    /// PATH and SPTH select a range of PNT and SPNT. BPNT has no path list of its own, so a bzs without
+    ///  any PNT uses the ranges of PATH for BPNT instead. Each PATH only ever selects from one of the two
+    pub fn from_bzs(name: String, bzs: &BZS) -> Self {
+        let mut paths = Vec::new();
+
+        let point_range = |start: u16, count: u16, len: usize| {
+            let start = start as usize;
+            (start..start + count as usize).filter(move |&i| i < len)
+        };
+        let uses_bpnt = bzs.pnt().is_empty() && !bzs.bpnt().is_empty();
+        for (index, path) in bzs.path().iter().enumerate() {
+            let (path_type, points) = if uses_bpnt {
+                // BPNT entries are (point, in handle, out handle)
+                let points =
+                    point_range(path.pnt_start_idx, path.pnt_total_count, bzs.bpnt().len())
+                        .map(|i| {
+                            let bpnt = &bzs.bpnt()[i];
+                            PathPoint {
+                                index: i,
+                                pos: Vec3::from_array(bpnt.pos1),
+                                handles: Some([
+                                    Vec3::from_array(bpnt.pos2),
+                                    Vec3::from_array(bpnt.pos3),
+                                ]),
+                                unk: bpnt.unk,
+                            }
+                        })
+                        .collect();
+                (PathType::BPNT, points)
+            } else {
+                let points = point_range(path.pnt_start_idx, path.pnt_total_count, bzs.pnt().len())
+                    .map(|i| PathPoint {
+                        index: i,
+                        pos: Vec3::from_array(bzs.pnt()[i].pos),
+                        handles: None,
+                        unk: bzs.pnt()[i].unk,
+                    })
+                    .collect();
+                (PathType::PATH, points)
+            };
+            paths.push(Path {
+                path_type,
+                index,
+                unk1: path.unk1,
+                unk2: path.unk2,
+                unk3: path.unk3,
+                points,
+            });
+        }
+        for (index, path) in bzs.spth().iter().enumerate() {
+            let points = point_range(path.pnt_start_idx, path.pnt_total_count, bzs.spnt().len())
+                .map(|i| PathPoint {
+                    index: i,
+                    pos: Vec3::from_array(bzs.spnt()[i].pos),
+                    handles: None,
+                    unk: bzs.spnt()[i].unk,
+                })
+                .collect();
+            paths.push(Path {
+                path_type: PathType::SPTH,
+                index,
+                unk1: path.unk1,
+                unk2: path.unk2,
+                unk3: path.unk3,
+                points,
+            });
+        }
+
+        let mut verts = Vec::new();
+        for path in &paths {
+            let clr = path.path_type.color();
+            for line in path.lines() {
+                let nrm = (line[1] - line[0]).normalize_or(Vec3::Y);
+                line.iter()
+                    .for_each(|&pos| verts.push(Vertex::new(pos, nrm, clr)));
+            }
+        }
+
+        Self {
+            name,
+            paths,
+            render: true,
+            mesh: Mesh::new(verts, glow::LINES),
+        }
+    }
+}
+
+impl Model for PathModel {
+    fn setup_gl(&mut self, gl: &glow::Context) {
+        self.mesh.setup_gl(gl);
+    }
+
+    fn destroy_gl(&mut self, gl: &glow::Context) {
+        self.mesh.destroy_gl(gl);
+    }
+
+    fn update_gl(&mut self, gl: &glow::Context) {
+        self.mesh.update_gl(gl);
+    }
+
+    fn draw(&mut self, gl: &glow::Context, shader: &Shader) {
+        if !self.render {
+            return;
+        }
+
+        self.mesh.draw(gl, shader);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    // PATH entries that select `count` points starting at `start`
+    fn path_chunk(ranges: &[(u16, u16)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (start, count) in ranges {
+            data.extend_from_slice(&[0xFF, 0xFF]);
+            data.extend_from_slice(&start.to_be_bytes());
+            data.extend_from_slice(&count.to_be_bytes());
+            data.extend_from_slice(&[0; 6]);
+        }
+        data
+    }
+
+    fn bpnt_chunk(count: u16) -> Vec<u8> {
+        (0..count)
+            .flat_map(|i| {
+                let x = i as f32 * 100.0;
+                let mut data = floats(&[x, 0.0, 0.0, x - 10.0, 0.0, 0.0, x + 10.0, 0.0, 0.0]);
+                data.extend_from_slice(&[0; 4]);
+                data
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paths_use_pnt_over_bpnt() {
+        let mut pnt = floats(&[1.0, 2.0, 3.0]);
+        pnt.extend_from_slice(&[0; 4]);
+        pnt.extend(floats(&[4.0, 5.0, 6.0]));
+        pnt.extend_from_slice(&[0; 4]);
+        let bzs = BZS::from_chunks(&[
+            (b"PATH", 1, path_chunk(&[(0, 2)])),
+            (b"PNT ", 2, pnt),
+            (b"BPNT", 3, bpnt_chunk(3)),
+        ]);
+
+        let model = PathModel::from_bzs("Paths".to_string(), &bzs);
+        assert_eq!(model.paths.len(), 1);
+        let path = &model.paths[0];
+        assert_eq!(path.path_type, PathType::PATH);
+        assert_eq!(path.points.len(), 2);
+        assert_eq!(path.points[1].pos, Vec3::new(4.0, 5.0, 6.0));
+        assert!(path.points.iter().all(|point| point.handles.is_none()));
+    }
+
+    #[test]
+    fn paths_fall_back_to_bpnt() {
+        let bzs = BZS::from_chunks(&[
+            (b"PATH", 2, path_chunk(&[(0, 2), (2, 1)])),
+            (b"BPNT", 3, bpnt_chunk(3)),
+        ]);
+
+        let model = PathModel::from_bzs("Paths".to_string(), &bzs);
+        assert_eq!(model.paths.len(), 2);
+        assert!(model
+            .paths
+            .iter()
+            .all(|path| path.path_type == PathType::BPNT));
+        assert_eq!(model.paths[1].index, 1);
+        assert_eq!(model.paths[1].points[0].index, 2);
+        assert_eq!(
+            model.paths[1].points[0].handles,
+            Some([Vec3::new(190.0, 0.0, 0.0), Vec3::new(210.0, 0.0, 0.0)])
+        );
+    }
+}
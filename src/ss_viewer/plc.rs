@@ -1,113 +1,214 @@
-use std::{fs, io::Write};
+use std::{error::Error, fmt, fs, io::Write, path::Path};
 
 use glam::Vec4;
+use serde::Deserialize;
 
 use crate::file_formats::{PLCEntry, PLC};
 
-#[derive(Debug, Clone)]
+// Copy of the schema used when the schema file can not be read
+const DEFAULT_SCHEMA: &str = include_str!("../../plc_schema.toml");
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct ShiftMask {
+    #[serde(rename = "code")]
     pub code_idx: usize,
     pub shift: u32,
     pub mask: u32,
 }
 
 impl ShiftMask {
-    const fn new(code_idx: usize, shift: u32, mask: u32) -> Self {
-        Self {
-            code_idx,
-            shift,
-            mask,
-        }
+    pub fn get(&self, entry: &PLCEntry) -> u32 {
+        (entry.codes[self.code_idx] >> self.shift) & self.mask
+    }
+
+    /// Bits of `value` outside of the mask are dropped
+    pub fn set(&self, entry: &mut PLCEntry, value: u32) {
+        let code = &mut entry.codes[self.code_idx];
+        *code = (*code & !(self.mask << self.shift)) | ((value & self.mask) << self.shift);
     }
 }
-pub enum EntryType {
-    Norm,
-    Range(ShiftMask),
-    Single(ShiftMask),
-}
-
-use EntryType::*;
-pub const ENTRY_FILTER: [EntryType; 32] = [
-    EntryType::Norm,
-    Range(ShiftMask::new(0, 00, 0x3F)), //  - 0x0000_003F |                 |
-    Range(ShiftMask::new(0, 06, 0xFF)), //  - 0x0000_3FC0 |                 |
-    Single(ShiftMask::new(0, 14, 0x1)), //  - 0x0000_4000 |  Pass Obj       |
-    Single(ShiftMask::new(0, 15, 0x1)), //  - 0x0000_8000 |  Pass Camera    |
-    Single(ShiftMask::new(0, 16, 0x1)), //  - 0x0001_0000 |  Pass Link      |
-    Single(ShiftMask::new(0, 17, 0x1)), //  - 0x0002_0000 |  Pass Arrow     |
-    Single(ShiftMask::new(0, 18, 0x1)), //  - 0x0004_0000 |  Pass Slingshot |
-    Single(ShiftMask::new(0, 19, 0x1)), //  - 0x0008_0000 |  Pass Beetle    |
-    Single(ShiftMask::new(0, 20, 0x1)), //  - 0x0010_0000 |  Pass Clawshot  |
-    Single(ShiftMask::new(0, 21, 0x1)), //  - 0x0020_0000 |  Pass Z-Target  |
-    Single(ShiftMask::new(0, 22, 0x1)), //  - 0x0040_0000 |  Pass Shadow    |
-    Single(ShiftMask::new(0, 23, 0x1)), //  - 0x0080_0000 |  Pass Bomb      |
-    Single(ShiftMask::new(0, 24, 0x1)), //  - 0x0100_0000 |  Pass Whip      |
-    Range(ShiftMask::new(0, 28, 0x3)),  //  - 0x3000_0000 |                 |
-    Single(ShiftMask::new(0, 30, 0x1)), //  - 0x4000_0000 |                 | 8034b7b0
-    Single(ShiftMask::new(0, 31, 0x1)), //  - 0x8000_0000 |                 |
-    Range(ShiftMask::new(1, 0, 0xFF)),  //  - 0x0000_00FF |                 |
-    Range(ShiftMask::new(1, 8, 0xF)),   //  - 0x0000_0F00 |                 |
-    Range(ShiftMask::new(1, 17, 0x7)),  //  - 0x000E_0000 |                 |
-    Range(ShiftMask::new(1, 20, 0x1F)), //  - 0x01F0_0000 | Ground Type     |
-    Single(ShiftMask::new(1, 25, 0x1)), //  - 0x0200_0000 |                 |
-    Single(ShiftMask::new(1, 26, 0x1)), //  - 0x0400_0000 |                 | 8034b7b0
-    Single(ShiftMask::new(1, 27, 0x1)), //  - 0x0800_0000 |                 | 8034b7b0
-    Range(ShiftMask::new(1, 28, 0xF)),  //  - 0xF000_0000 |                 | 8034b7b0
-    Range(ShiftMask::new(2, 0, 0xFF)),  //  - 0x0000_00FF |                 |
-    Range(ShiftMask::new(2, 8, 0xFF)),  //  - 0x0000_FF00 |                 |
-    Range(ShiftMask::new(2, 16, 0xFF)), //  - 0x00FF_0000 |                 |
-    Range(ShiftMask::new(2, 24, 0xFF)), //  - 0xFF00_0000 |                 |
-    Range(ShiftMask::new(3, 0, 0x1F)),  //  - 0x0000_001F | limited to 0-31 | 0xC is Vines
-    Range(ShiftMask::new(3, 5, 0x3F)),  //  - 0x0000_07E0 | limited to 0-31 | 0xC is Vines
-    Range(ShiftMask::new(4, 0, 0xFFFFFFFF)), //  - 0x0000_07E0 | limited to 0-31 | 0xC is Vines
-];
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-//                                               Pass Flags                                                          //
+//                                               Field Schema                                                        //
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-#[allow(dead_code)]
-impl PLCEntry {
-    pub fn get_pass_object(&self) -> bool {
-        self.codes[0] & 0x0000_4000 != 0
+/// A known value of a field
+#[derive(Debug, Clone, Deserialize)]
+pub struct PLCValue {
+    pub value: u32,
+    pub name: String,
+    pub color: Option<[f32; 3]>,
+}
+
+/// A field of the PLC entries, as described in the schema file
+#[derive(Debug, Clone, Deserialize)]
+pub struct PLCField {
+    pub name: String,
+    key: Option<String>, // Name of the field in filter expressions
+    #[serde(flatten)]
+    pub bits: ShiftMask,
+    #[serde(default)]
+    pub values: Vec<PLCValue>,
+}
+
+impl PLCField {
+    /// The `key` from the schema, or the name in snake case ("Pass Link" -> "pass_link")
+    pub fn key(&self) -> String {
+        match &self.key {
+            Some(key) => key.clone(),
+            None => self
+                .name
+                .to_lowercase()
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join("_"),
+        }
     }
-    pub fn get_pass_camera(&self) -> bool {
-        self.codes[0] & 0x0000_8000 != 0
+
+    /// Single bit fields are shown as on/off instead of as a range
+    pub fn is_flag(&self) -> bool {
+        self.bits.mask == 1
     }
-    pub fn get_pass_link(&self) -> bool {
-        self.codes[0] & 0x0001_0000 != 0
+
+    pub fn value(&self, value: u32) -> Option<&PLCValue> {
+        self.values.iter().find(|known| known.value == value)
     }
-    pub fn get_pass_arrow(&self) -> bool {
-        self.codes[0] & 0x0002_0000 != 0
+
+    /// Name of `value` from the schema, or the value itself in hex
+    pub fn value_name(&self, value: u32) -> String {
+        match self.value(value) {
+            Some(known) => format!("{} (0x{value:X})", known.name),
+            None => format!("0x{value:X}"),
+        }
     }
-    pub fn get_pass_slingshot(&self) -> bool {
-        self.codes[0] & 0x0004_0000 != 0
+}
+
+#[derive(Debug, Clone)]
+enum PLCSchemaError {
+    CodeOutOfRange(String),
+    MaskOutOfCode(String),
+    ValueOutOfMask(String, u32),
+}
+impl fmt::Display for PLCSchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CodeOutOfRange(name) => write!(f, "PLCSchemaError: {name}: code must be 0 to 4"),
+            Self::MaskOutOfCode(name) => {
+                write!(
+                    f,
+                    "PLCSchemaError: {name}: mask is empty or shifted out of the code"
+                )
+            }
+            Self::ValueOutOfMask(name, value) => {
+                write!(
+                    f,
+                    "PLCSchemaError: {name}: value 0x{value:X} does not fit the mask"
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for PLCSchemaError {}
+
+/// Every field that can be used as a Property Filter
+#[derive(Debug, Clone, Deserialize)]
+pub struct PLCSchema {
+    pub fields: Vec<PLCField>,
+}
+
+impl PLCSchema {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::from_toml(&fs::read_to_string(path)?)
     }
-    pub fn get_pass_beetle(&self) -> bool {
-        self.codes[0] & 0x0008_0000 != 0
+
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        let schema: Self = toml::from_str(text)?;
+        for field in &schema.fields {
+            let ShiftMask {
+                code_idx,
+                shift,
+                mask,
+            } = field.bits;
+            if code_idx >= 5 {
+                return Err(PLCSchemaError::CodeOutOfRange(field.name.clone()).into());
+            }
+            if mask == 0 || shift >= 32 || (mask as u64) << shift > u32::MAX as u64 {
+                return Err(PLCSchemaError::MaskOutOfCode(field.name.clone()).into());
+            }
+            if let Some(known) = field.values.iter().find(|known| known.value & !mask != 0) {
+                return Err(PLCSchemaError::ValueOutOfMask(field.name.clone(), known.value).into());
+            }
+        }
+        Ok(schema)
     }
-    pub fn get_pass_clawshot(&self) -> bool {
-        self.codes[0] & 0x0010_0000 != 0
+
+    /// Reads the schema file, falling back to the schema built into the viewer
+    pub fn load(path: &Path) -> Self {
+        Self::from_file(path).unwrap_or_else(|e| {
+            println!(
+                "{}: Could not read PLC schema, using the default: {e}",
+                path.display()
+            );
+            Self::from_toml(DEFAULT_SCHEMA).expect("The default PLC schema should be valid")
+        })
     }
-    pub fn get_pass_target(&self) -> bool {
-        self.codes[0] & 0x0020_0000 != 0
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                               Typed Access                                                        //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Conversion between the typed value of a field and its bits
+pub trait FieldValue {
+    fn from_bits(bits: u32) -> Self;
+    fn into_bits(self) -> u32;
+}
+
+impl FieldValue for bool {
+    fn from_bits(bits: u32) -> Self {
+        bits != 0
     }
-    pub fn get_pass_shadow(&self) -> bool {
-        self.codes[0] & 0x0040_0000 != 0
+    fn into_bits(self) -> u32 {
+        self as u32
     }
-    pub fn get_pass_bomb(&self) -> bool {
-        self.codes[0] & 0x0080_0000 != 0
+}
+
+impl FieldValue for u8 {
+    fn from_bits(bits: u32) -> Self {
+        bits as u8
     }
-    pub fn get_pass_whip(&self) -> bool {
-        self.codes[0] & 0x0100_0000 != 0
+    fn into_bits(self) -> u32 {
+        self as u32
     }
 }
 
-///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-//                                               Other Attributes                                                    //
-///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl FieldValue for u32 {
+    fn from_bits(bits: u32) -> Self {
+        bits
+    }
+    fn into_bits(self) -> u32 {
+        self
+    }
+}
+
+// The layout of every field only lives in the schema, so fields are looked up by their key (ex: "ground")
+impl PLCSchema {
+    pub fn field(&self, key: &str) -> Option<&PLCField> {
+        self.fields.iter().find(|field| field.key() == key)
+    }
+}
+
+impl PLCField {
+    pub fn get<T: FieldValue>(&self, entry: &PLCEntry) -> T {
+        T::from_bits(self.bits.get(entry))
+    }
 
-impl PLCEntry {}
+    /// Bits of `value` outside of the mask are dropped
+    #[allow(dead_code)] // Nothing edits the entries yet
+    pub fn set<T: FieldValue>(&self, entry: &mut PLCEntry, value: T) {
+        self.bits.set(entry, value.into_bits());
+    }
+}
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //                                               Misc                                                                //
@@ -126,38 +227,174 @@ impl PLC {
     }
 }
 
+// Distinct colors for the values of a field, values past the end get a generated hue
+const PALETTE: [Vec4; 12] = [
+    Vec4::new(0.12, 0.47, 0.71, 1.0),
+    Vec4::new(1.00, 0.50, 0.05, 1.0),
+    Vec4::new(0.17, 0.63, 0.17, 1.0),
+    Vec4::new(0.84, 0.15, 0.16, 1.0),
+    Vec4::new(0.58, 0.40, 0.74, 1.0),
+    Vec4::new(0.55, 0.34, 0.29, 1.0),
+    Vec4::new(0.89, 0.47, 0.76, 1.0),
+    Vec4::new(0.50, 0.50, 0.50, 1.0),
+    Vec4::new(0.74, 0.74, 0.13, 1.0),
+    Vec4::new(0.09, 0.75, 0.81, 1.0),
+    Vec4::new(0.68, 0.78, 0.91, 1.0),
+    Vec4::new(1.00, 0.73, 0.47, 1.0),
+];
+// Color of the entries that do not have the isolated value
+const ISOLATE_COLOR: Vec4 = Vec4::new(0.15, 0.15, 0.15, 1.0);
+
+fn palette_color(value: u32) -> Vec4 {
+    if let Some(clr) = PALETTE.get(value as usize) {
+        return *clr;
+    }
+    // Golden ratio steps keep consecutive hues far apart
+    let hue = (value as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    Vec4::new(r, g, b, 1.0) * 0.8 + Vec4::new(0.1, 0.1, 0.1, 0.2)
+}
+
+impl PLCField {
+    /// Color of `value`: the one from the schema, on/off for flags or a color of the palette
+    pub fn color(&self, value: u32) -> Vec4 {
+        if let Some([r, g, b]) = self.value(value).and_then(|known| known.color) {
+            return Vec4::new(r, g, b, 1.0f32);
+        }
+        match (self.is_flag(), value) {
+            (true, 1) => Vec4::new(0.0f32, 0.7f32, 0.0f32, 1.0f32),
+            (true, _) => Vec4::new(1f32, 1f32, 1f32, 1f32),
+            (false, _) => palette_color(value),
+        }
+    }
+}
+
 impl PLCEntry {
-    pub fn get_color(&self, filter_type: usize, range_selection: u32) -> Option<Vec4> {
-        let val = ENTRY_FILTER.get(filter_type);
-        if let Some(val) = val {
-            match *val {
-                Norm => return None,
-                Range(ShiftMask {
-                    code_idx,
-                    shift,
-                    mask,
-                }) => {
-                    let code = (self.codes[code_idx] >> shift) & mask;
-                    if code == range_selection {
-                        return Some(Vec4::ZERO.with_w(1.0f32).with_y(0.6f32));
-                    }
-                    return Some(Vec4::splat((code as f32) / (mask as f32)).with_w(1.0f32));
-                }
-                Single(ShiftMask {
-                    code_idx,
-                    shift,
-                    mask,
-                }) => {
-                    let code = (self.codes[code_idx] >> shift) & mask;
-
-                    if (code & 1) == 1 {
-                        return Some(Vec4::new(0.0f32, 0.7f32, 0.0f32, 1.0f32));
-                    } else {
-                        return Some(Vec4::new(1f32, 1f32, 1f32, 1f32));
-                    }
-                }
-            };
+    /// Color of the entry for `field`. `None` when there is no field, to use the normals instead.
+    ///  When a value is isolated, entries with any other value are greyed out
+    pub fn get_color(&self, field: Option<&PLCField>, isolated: Option<u32>) -> Option<Vec4> {
+        let field = field?;
+        let value = field.get(self);
+        if isolated.is_some_and(|isolated| isolated != value) {
+            return Some(ISOLATE_COLOR);
         }
-        None
+        Some(field.color(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_schema() -> PLCSchema {
+        PLCSchema::from_toml(DEFAULT_SCHEMA).expect("Should be valid")
+    }
+
+    #[test]
+    fn setters_only_touch_their_field() {
+        let schema = default_schema();
+        let field = |key: &str| schema.field(key).expect("Should be in the schema");
+        let mut entry = PLCEntry { codes: [0; 5] };
+        field("pass_link").set(&mut entry, true);
+        field("ground").set(&mut entry, 0x3Fu8); // Too large, only 5 bits are kept
+        field("climb").set(&mut entry, 0xCu8);
+        field("code_2_0x00ff_0000").set(&mut entry, 0xABu8);
+        assert_eq!(entry.codes, [0x0001_0000, 0x01F0_0000, 0x00AB_0000, 0xC, 0]);
+
+        assert!(field("pass_link").get::<bool>(&entry));
+        assert!(!field("pass_object").get::<bool>(&entry));
+        assert_eq!(field("ground").get::<u8>(&entry), 0x1F);
+        assert_eq!(
+            field("climb").value_name(field("climb").get(&entry)),
+            "Vines (0xC)"
+        );
+
+        field("pass_link").set(&mut entry, false);
+        assert_eq!(entry.codes[0], 0);
+    }
+
+    #[test]
+    fn default_schema_keeps_the_known_layouts() {
+        // The fields the viewer is known by. A schema update that moves them should be deliberate
+        let schema = default_schema();
+        let known = [
+            ("pass_object", 0, 14, 0x1),
+            ("pass_camera", 0, 15, 0x1),
+            ("pass_link", 0, 16, 0x1),
+            ("pass_arrow", 0, 17, 0x1),
+            ("pass_slingshot", 0, 18, 0x1),
+            ("pass_beetle", 0, 19, 0x1),
+            ("pass_clawshot", 0, 20, 0x1),
+            ("pass_z_target", 0, 21, 0x1),
+            ("pass_shadow", 0, 22, 0x1),
+            ("pass_bomb", 0, 23, 0x1),
+            ("pass_whip", 0, 24, 0x1),
+            ("ground", 1, 20, 0x1F),
+            ("code_2_0x0000_00ff", 2, 0, 0xFF),
+            ("code_2_0x0000_ff00", 2, 8, 0xFF),
+            ("code_2_0x00ff_0000", 2, 16, 0xFF),
+            ("code_2_0xff00_0000", 2, 24, 0xFF),
+            ("climb", 3, 0, 0x1F),
+            ("code_4", 4, 0, 0xFFFF_FFFF),
+        ];
+        for (key, code_idx, shift, mask) in known {
+            let bits = &schema
+                .field(key)
+                .unwrap_or_else(|| panic!("Missing {key}"))
+                .bits;
+            assert_eq!(
+                (bits.code_idx, bits.shift, bits.mask),
+                (code_idx, shift, mask),
+                "{key}"
+            );
+        }
+    }
+
+    #[test]
+    fn default_schema_is_valid() {
+        let schema = default_schema();
+        let climb = schema.field("climb").expect("Should have the climb type");
+        assert_eq!(climb.value_name(0xC), "Vines (0xC)");
+        assert_eq!(climb.value_name(0x3), "0x3");
+
+        let entry = PLCEntry {
+            codes: [0, 0, 0, 0xC, 0],
+        };
+        assert_eq!(
+            entry.get_color(Some(climb), None),
+            Some(Vec4::new(0.1, 0.6, 0.1, 1.0))
+        );
+        assert_eq!(entry.get_color(Some(climb), Some(0x1)), Some(ISOLATE_COLOR));
+        assert_eq!(entry.get_color(None, None), None);
+    }
+
+    #[test]
+    fn palette_colors_are_distinct() {
+        let colors: Vec<Vec4> = (0..64).map(palette_color).collect();
+        for (i, a) in colors.iter().enumerate() {
+            for b in &colors[i + 1..] {
+                assert!(a.distance(*b) > 0.01, "{a} and {b} are too close");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let field = |bits: &str| format!("[[fields]]\nname = \"Test\"\n{bits}\n");
+        assert!(PLCSchema::from_toml(&field("code = 5\nshift = 0\nmask = 0x1")).is_err());
+        assert!(PLCSchema::from_toml(&field("code = 0\nshift = 30\nmask = 0xF")).is_err());
+        assert!(PLCSchema::from_toml(&field(
+            "code = 0\nshift = 0\nmask = 0x3\nvalues = [{ value = 4, name = \"Big\" }]"
+        ))
+        .is_err());
+        assert!(PLCSchema::from_toml(&field("code = 4\nshift = 0\nmask = 0xFFFF_FFFF")).is_ok());
     }
 }
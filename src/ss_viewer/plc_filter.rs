@@ -0,0 +1,314 @@
+use std::{error::Error, fmt};
+
+use crate::file_formats::PLCEntry;
+
+use super::plc::{PLCSchema, ShiftMask};
+
+// Number of codes in a PLC entry, each one can be used whole as `code0` to `code4`
+const CODE_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitAnd,
+}
+
+impl Operator {
+    fn apply(&self, lhs: u32, rhs: u32) -> u32 {
+        let result = match self {
+            Self::Or => lhs != 0 || rhs != 0,
+            Self::And => lhs != 0 && rhs != 0,
+            Self::Eq => lhs == rhs,
+            Self::Ne => lhs != rhs,
+            Self::Lt => lhs < rhs,
+            Self::Le => lhs <= rhs,
+            Self::Gt => lhs > rhs,
+            Self::Ge => lhs >= rhs,
+            Self::BitAnd => return lhs & rhs,
+        };
+        result as u32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(u32),
+    Ident(String),
+    Operator(Operator),
+    Not,
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Value(u32),
+    Field(ShiftMask),
+    Not(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, entry: &PLCEntry) -> u32 {
+        match self {
+            Self::Value(value) => *value,
+            Self::Field(bits) => bits.get(entry),
+            Self::Not(expr) => (expr.eval(entry) == 0) as u32,
+            Self::Binary(op, lhs, rhs) => op.apply(lhs.eval(entry), rhs.eval(entry)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PLCFilterError {
+    Character(char),
+    Number(String),
+    UnknownField(String),
+    Unexpected(String),
+    End,
+}
+impl fmt::Display for PLCFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Character(c) => write!(f, "PLCFilterError: Unexpected character '{c}'"),
+            Self::Number(text) => write!(f, "PLCFilterError: Invalid number \"{text}\""),
+            Self::UnknownField(name) => write!(f, "PLCFilterError: Unknown field \"{name}\""),
+            Self::Unexpected(token) => write!(f, "PLCFilterError: Unexpected {token}"),
+            Self::End => write!(f, "PLCFilterError: Unexpected end of the expression"),
+        }
+    }
+}
+impl std::error::Error for PLCFilterError {}
+
+/// A boolean expression over the fields of the PLC entries,
+///  ex: `ground == 5 && pass_link && !pass_camera`
+///
+/// Fields are the keys of the schema or `code0` to `code4` for a whole code. Numbers can be decimal or hex (`0x`).
+///  Supports `||`, `&&`, `!`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `&` and parentheses with the precedence of Rust
+#[derive(Debug, Clone)]
+pub struct PLCFilter {
+    expr: Expr,
+}
+
+impl PLCFilter {
+    pub fn parse(text: &str, schema: &PLCSchema) -> Result<Self, Box<dyn Error>> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            schema,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(PLCFilterError::Unexpected(format!("{token:?}")).into());
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn matches(&self, entry: &PLCEntry) -> bool {
+        self.expr.eval(entry) != 0
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PLCFilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        // Operators that may be followed by a second character
+        let mut pair = |second: char, single: Option<Token>, double: Token| {
+            if chars.next_if_eq(&second).is_some() {
+                Ok(double)
+            } else {
+                single.ok_or(PLCFilterError::Character(c))
+            }
+        };
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '|' => pair('|', None, Token::Operator(Operator::Or))?,
+            '&' => pair(
+                '&',
+                Some(Token::Operator(Operator::BitAnd)),
+                Token::Operator(Operator::And),
+            )?,
+            '=' => pair('=', None, Token::Operator(Operator::Eq))?,
+            '!' => pair('=', Some(Token::Not), Token::Operator(Operator::Ne))?,
+            '<' => pair(
+                '=',
+                Some(Token::Operator(Operator::Lt)),
+                Token::Operator(Operator::Le),
+            )?,
+            '>' => pair(
+                '=',
+                Some(Token::Operator(Operator::Gt)),
+                Token::Operator(Operator::Ge),
+            )?,
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                if c.is_ascii_digit() {
+                    let digits = word.replace('_', "");
+                    let number = match digits.strip_prefix("0x") {
+                        Some(hex) => u32::from_str_radix(hex, 16),
+                        None => digits.parse(),
+                    };
+                    Token::Number(number.map_err(|_| PLCFilterError::Number(word))?)
+                } else {
+                    Token::Ident(word)
+                }
+            }
+            c => return Err(PLCFilterError::Character(c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// Recursive descent, one function per precedence level
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    schema: &'a PLCSchema,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Result<Token, PLCFilterError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or(PLCFilterError::End)?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    // Consumes the next token if it is one of `ops`
+    fn operator(&mut self, ops: &[Operator]) -> Option<Operator> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Operator(op)) if ops.contains(op) => {
+                self.pos += 1;
+                Some(*op)
+            }
+            _ => None,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[Operator],
+        next: fn(&mut Self) -> Result<Expr, PLCFilterError>,
+    ) -> Result<Expr, PLCFilterError> {
+        let mut lhs = next(self)?;
+        while let Some(op) = self.operator(ops) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(next(self)?));
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr, PLCFilterError> {
+        self.binary(&[Operator::Or], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, PLCFilterError> {
+        self.binary(&[Operator::And], Self::comparison)
+    }
+
+    // Comparisons can not be chained
+    fn comparison(&mut self) -> Result<Expr, PLCFilterError> {
+        use Operator::*;
+        let lhs = self.bit_and()?;
+        match self.operator(&[Eq, Ne, Lt, Le, Gt, Ge]) {
+            Some(op) => Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.bit_and()?))),
+            None => Ok(lhs),
+        }
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, PLCFilterError> {
+        self.binary(&[Operator::BitAnd], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, PLCFilterError> {
+        match self.next()? {
+            Token::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            Token::Number(value) => Ok(Expr::Value(value)),
+            Token::Ident(name) => self.field(name),
+            Token::Open => {
+                let expr = self.or()?;
+                match self.next()? {
+                    Token::Close => Ok(expr),
+                    token => Err(PLCFilterError::Unexpected(format!("{token:?}"))),
+                }
+            }
+            token => Err(PLCFilterError::Unexpected(format!("{token:?}"))),
+        }
+    }
+
+    fn field(&self, name: String) -> Result<Expr, PLCFilterError> {
+        if let Some(field) = self.schema.field(&name) {
+            return Ok(Expr::Field(field.bits.clone()));
+        }
+        match name.strip_prefix("code").and_then(|idx| idx.parse().ok()) {
+            Some(code_idx) if code_idx < CODE_COUNT => Ok(Expr::Field(ShiftMask {
+                code_idx,
+                shift: 0,
+                mask: u32::MAX,
+            })),
+            _ => Err(PLCFilterError::UnknownField(name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> PLCSchema {
+        PLCSchema::from_toml(include_str!("../../plc_schema.toml")).expect("Should be valid")
+    }
+
+    fn entry(codes: [u32; 5]) -> PLCEntry {
+        PLCEntry { codes }
+    }
+
+    #[test]
+    fn evaluates_fields_and_operators() {
+        let schema = schema();
+        let filter = PLCFilter::parse("ground == 5 && pass_link && !pass_camera", &schema)
+            .expect("Should parse");
+        assert!(filter.matches(&entry([0x0001_0000, 0x0050_0000, 0, 0, 0])));
+        assert!(!filter.matches(&entry([0x0001_8000, 0x0050_0000, 0, 0, 0])));
+        assert!(!filter.matches(&entry([0x0001_0000, 0x0040_0000, 0, 0, 0])));
+
+        let filter = PLCFilter::parse("(code1 & 0xF00) >= 0x200 || climb == 0xC", &schema)
+            .expect("Should parse");
+        assert!(filter.matches(&entry([0, 0x300, 0, 0, 0])));
+        assert!(filter.matches(&entry([0, 0, 0, 0xC, 0])));
+        assert!(!filter.matches(&entry([0, 0x100, 0, 0, 0])));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let schema = schema();
+        for text in [
+            "",
+            "ground ==",
+            "unknown_field",
+            "pass_link | pass_bomb",
+            "(ground",
+            "code5",
+            "1 2",
+        ] {
+            assert!(PLCFilter::parse(text, &schema).is_err(), "{text}");
+        }
+    }
+}
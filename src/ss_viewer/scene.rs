@@ -1,27 +1,218 @@
 use core::fmt;
-use std::{error::Error, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
 
 use eframe::glow;
-use glam::{Mat4, Vec3Swizzles};
+use glam::{Mat4, Vec3, Vec3Swizzles, Vec4};
 
-use crate::gfx::{camera::Camera, Model, Shader};
+use crate::{
+    file_formats::{
+        bzs::{LAYER_COUNT, LYLT, LYSE},
+        lz11, LayerData, PLCEntry, U8Archive, BZS,
+    },
+    gfx::{camera::Camera, Model, Shader},
+};
 
-use super::{DZBModel, KCLModel};
+use super::{
+    actor_model::{Actor, ActorType},
+    entrance_graph::StageExit,
+    event_table::{Event, EventTable},
+    plc::PLCField,
+    plc_filter::PLCFilter,
+    ActorModel, AreaModel, CameraModel, DZBModel, KCLModel, PathModel, SpawnModel,
+};
 
 #[derive(Debug, Clone, Default)]
 struct SceneNode {
     name: String,             // Name of the node (ex: "Room #")
     children: Vec<SceneNode>, // Can contain more children
     render: bool,
-    kcl_model_idx: Vec<usize>, // Into kcl_models
+    kcl_model_idx: Vec<usize>,    // Into kcl_models
+    dzb_model_idx: Vec<usize>,    // Into dzb_models
+    actor_model_idx: Vec<usize>,  // Into actor_models
+    area_model_idx: Vec<usize>,   // Into area_models
+    path_model_idx: Vec<usize>,   // Into path_models
+    spawn_model_idx: Vec<usize>,  // Into spawn_models
+    camera_model_idx: Vec<usize>, // Into camera_models
+    bzs: Option<BZS>,             // Stage/Room data placed next to the collision
+    object_arc: bool,             // An object archive (oarc), only loaded when a layer asks for it
+    room_state: RoomState,
+    link_hidden: bool, // Hidden by "Only Show Linked Rooms", kept apart from `render` so it can be undone
+    object_instances: Vec<ObjectInstance>, // Object collision placed by the actors of the node
+}
+
+// Collision of an object archive placed at the position of an actor
+#[derive(Debug, Clone)]
+struct ObjectInstance {
+    actor_model_idx: usize,    // Into actor_models
+    actor_idx: usize,          // Into the actors of the actor model
     dzb_model_idx: Vec<usize>, // Into dzb_models
 }
 
+// How a room relates to the room selected in the room links (RMPL)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum RoomState {
+    #[default]
+    Unrelated,
+    Selected,
+    Linked, // Kept loaded by the game together with the selected room
+}
+
 impl SceneNode {
     fn with_name(mut self, name: String) -> Self {
         self.name = name;
         self
     }
+
+    fn for_each_bzs<'a>(&'a self, f: &mut impl FnMut(&'a BZS)) {
+        if let Some(bzs) = &self.bzs {
+            f(bzs);
+        }
+        self.children.iter().for_each(|node| node.for_each_bzs(f));
+    }
+
+    // Rooms are named "Room #"
+    fn room_id(&self) -> Option<usize> {
+        self.name.strip_prefix("Room ")?.parse().ok()
+    }
+
+    fn for_each_node_mut(&mut self, f: &mut impl FnMut(&mut SceneNode)) {
+        f(self);
+        self.children
+            .iter_mut()
+            .for_each(|node| node.for_each_node_mut(f));
+    }
+
+    // Same as `for_each_bzs`, also giving the name of the node holding the bzs
+    fn for_each_named_bzs<'a>(&'a self, f: &mut impl FnMut(&'a str, &'a BZS)) {
+        if let Some(bzs) = &self.bzs {
+            f(&self.name, bzs);
+        }
+        self.children
+            .iter()
+            .for_each(|node| node.for_each_named_bzs(f));
+    }
+
+    // Enabled by the user and not hidden by the room links
+    fn visible(&self) -> bool {
+        self.render && !self.link_hidden
+    }
+
+    // Every node that is currently rendered (the node and all its parents are visible)
+    fn rendered_nodes<'a>(&'a self, out: &mut Vec<&'a SceneNode>) {
+        if self.visible() {
+            out.push(self);
+            self.children
+                .iter()
+                .for_each(|node| node.rendered_nodes(out));
+        }
+    }
+}
+
+// Decides which parts of the stage data are shown
+#[derive(Debug, Clone)]
+struct SceneFilter {
+    actor_type_render: [bool; ActorType::ALL.len()],
+    area_render: bool,
+    path_render: bool,
+    point_labels: bool, // Index of every path point
+    spawn_render: bool,
+    camera_render: bool,
+    object_render: bool,  // Object collision placed at the actors
+    layer: Option<usize>, // None shows every layer
+    // Object archives (ARCN) of the selected layer. None when objects are not filtered
+    loaded_arcs: Option<Vec<String>>,
+}
+
+impl SceneFilter {
+    fn new() -> Self {
+        Self {
+            actor_type_render: [true; ActorType::ALL.len()],
+            area_render: true,
+            path_render: true,
+            point_labels: true,
+            spawn_render: true,
+            camera_render: true,
+            object_render: true,
+            layer: None,
+            loaded_arcs: None,
+        }
+    }
+
+    // Layer 0 is always loaded together with the selected layer
+    fn shows_layer(&self, layer: Option<usize>) -> bool {
+        match (self.layer, layer) {
+            (None, _) | (_, None) | (_, Some(0)) => true,
+            (Some(selected), Some(layer)) => selected == layer,
+        }
+    }
+
+    fn shows_actor(&self, actor: &Actor) -> bool {
+        self.actor_type_render[actor.actor_type as usize] && self.shows_layer(actor.layer)
+    }
+
+    fn shows_object(&self, name: &str) -> bool {
+        self.loaded_arcs
+            .as_ref()
+            .is_none_or(|arcs| arcs.iter().any(|arc| arc == name))
+    }
+}
+
+// Which LYSE entry is used when several apply. What the game does is not confirmed, so it is a choice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum LyseOrder {
+    FirstMatch,
+    #[default]
+    LastMatch,
+}
+
+// Game progress entered by the user to find the layer the game would load
+#[derive(Debug, Clone, Default)]
+struct StoryState {
+    story_flags: String, // Active story flags separated by commas or spaces
+    night: bool,
+    order: LyseOrder,
+}
+
+impl StoryState {
+    // Flags can be written in decimal or in hexadecimal (0x). Invalid ones are ignored
+    fn story_flags(&self) -> Vec<i16> {
+        self.story_flags
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|flag| match flag.strip_prefix("0x") {
+                Some(hex) => i16::from_str_radix(hex, 16).ok(),
+                None => flag.parse().ok(),
+            })
+            .collect()
+    }
+
+    // The entries that apply, in file order. An entry applies when its story flag is active
+    //  (a negative flag needs no flag) and its night matches
+    fn matching<'a>(&self, lyse: &[&'a LYSE]) -> Vec<&'a LYSE> {
+        let flags = self.story_flags();
+        lyse.iter()
+            .copied()
+            .filter(|entry| {
+                (entry.story_flag < 0 || flags.contains(&entry.story_flag))
+                    && (entry.night != 0) == self.night
+            })
+            .collect()
+    }
+
+    // The layer of the first or last entry that applies, depending on `order`. None when no entry applies
+    fn layer(&self, lyse: &[&LYSE]) -> Option<usize> {
+        let matching = self.matching(lyse);
+        let entry = match self.order {
+            LyseOrder::FirstMatch => matching.first(),
+            LyseOrder::LastMatch => matching.last(),
+        };
+        entry.map(|entry| entry.layer as usize)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +222,17 @@ pub struct Scene {
 
     kcl_models: Vec<KCLModel>,
     dzb_models: Vec<DZBModel>,
+    actor_models: Vec<ActorModel>,
+    area_models: Vec<AreaModel>,
+    path_models: Vec<PathModel>,
+    spawn_models: Vec<SpawnModel>,
+    camera_models: Vec<CameraModel>,
+
+    filter: SceneFilter,
+    story_state: StoryState,
+    selected_room: Option<usize>,
+    link_room_visibility: bool, // Only render the selected room and the rooms linked to it
+    event_table: EventTable,
 
     root_node: SceneNode,
 }
@@ -42,6 +244,9 @@ pub struct Scene {
 #[derive(Debug)]
 enum SceneError {
     InvalidRoot(PathBuf),
+    MissingArchive(PathBuf, String),
+    InvalidName(PathBuf),
+    EmptyArchive(String),
 }
 impl fmt::Display for SceneError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -51,6 +256,19 @@ impl fmt::Display for SceneError {
                 "Scene Root was not given a directory: {}",
                 path.display()
             ),
+            Self::MissingArchive(path, name) => write!(
+                f,
+                "Could not find {name} in {} (or its NX folder)",
+                path.display()
+            ),
+            Self::InvalidName(path) => {
+                write!(
+                    f,
+                    "Stage folder name is not valid UTF-8: {}",
+                    path.display()
+                )
+            }
+            Self::EmptyArchive(name) => write!(f, "{name} has no collision or stage data"),
         }
     }
 }
@@ -77,6 +295,7 @@ impl Scene {
         let mut kcl_files = Vec::new();
         let mut plc_files = Vec::new();
         let mut dzb_files = Vec::new();
+        let mut bzs_files = Vec::new();
 
         if let Ok(dirs) = dir.read_dir() {
             for dir in dirs {
@@ -91,6 +310,7 @@ impl Scene {
                             "kcl" => kcl_files.push(path),
                             "dzb" => dzb_files.push(path),
                             "plc" => plc_files.push(path),
+                            "bzs" => bzs_files.push(path),
                             _ => {}
                         }
                     }
@@ -147,20 +367,66 @@ impl Scene {
             }
         }
 
+        // Stage/Room data (stage.bzs or room.bzs) sits next to the collision. It is added first so a node
+        //  without collision is still kept for it
+        if let Some(bzs_path) = bzs_files.first() {
+            let bzs: Result<BZS, Box<dyn Error>> = fs::read(bzs_path)
+                .map_err(Box::from)
+                .and_then(|data| BZS::read(&mut Cursor::new(data)));
+            match bzs {
+                Ok(bzs) => self.add_bzs(&mut node, bzs),
+                Err(e) => println!("Unable to read {}: {e}", bzs_path.display()),
+            }
+        }
+        self.finish_node(node)
+    }
+
+    // Keeps the bzs with the node and builds the markers of its actors
+    fn add_bzs(&mut self, node: &mut SceneNode, bzs: BZS) {
+        let actor_model = ActorModel::from_bzs("Actors".to_string(), &bzs);
+        if !actor_model.actors.is_empty() {
+            node.actor_model_idx.push(self.actor_models.len());
+            self.actor_models.push(actor_model);
+        }
+        if !bzs.area().is_empty() {
+            node.area_model_idx.push(self.area_models.len());
+            self.area_models
+                .push(AreaModel::from_bzs("Areas".to_string(), &bzs));
+        }
+        let path_model = PathModel::from_bzs("Paths".to_string(), &bzs);
+        if !path_model.paths.is_empty() {
+            node.path_model_idx.push(self.path_models.len());
+            self.path_models.push(path_model);
+        }
+        if !bzs.ply().is_empty() {
+            node.spawn_model_idx.push(self.spawn_models.len());
+            self.spawn_models
+                .push(SpawnModel::from_bzs("Spawns".to_string(), &bzs));
+        }
+        let camera_model = CameraModel::from_bzs("Cameras".to_string(), &bzs);
+        if !camera_model.is_empty() {
+            node.camera_model_idx.push(self.camera_models.len());
+            self.camera_models.push(camera_model);
+        }
+        node.bzs = Some(bzs);
+    }
+
+    // Decides what is rendered by default once all the models and children are added to a node
+    fn finish_node(&mut self, mut node: SceneNode) -> Option<SceneNode> {
         // Rendering the node is based off of:
-        //  1. Containing room models -> Always Render
+        //  1. Containing room models or stage data (bzs) -> Always Render
         //  2. Containing Children -> Always Render
         //  3. Containing ONLY Dzb -> Disable node, enable dzbs
         //  4. Constaining a mix with/DZB -> Enable the rest, but disable dzb
 
-        // 5. If containing no children or models, dont include the node (None)
+        // 5. If containing no children, models or bzs, dont include the node (None)
 
         let mut render_node = true;
-        if node.kcl_model_idx.len() != 0 || node.children.len() != 0 {
+        if !node.kcl_model_idx.is_empty() || !node.children.is_empty() || node.bzs.is_some() {
             node.dzb_model_idx.iter().for_each(|&index| {
                 self.dzb_models.get_mut(index).unwrap().render = false;
             });
-        } else if node.dzb_model_idx.len() != 0 {
+        } else if !node.dzb_model_idx.is_empty() {
             render_node = false;
             node.dzb_model_idx.iter().for_each(|&index| {
                 self.dzb_models.get_mut(index).unwrap().render = true;
@@ -174,16 +440,418 @@ impl Scene {
     }
 }
 
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                   Build The Scene Based on a game extract                                         //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Finds an archive in either the SD layout or the HD layout (NX folder), preferring the compressed one
+fn find_archive(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("NX")]
+        .iter()
+        .flat_map(|dir| [dir.join(format!("{file_name}.LZ")), dir.join(file_name)])
+        .find(|path| path.is_file())
+}
+
+// Reads an archive from disk, decompressing it first when it is a `.LZ` file
+fn read_archive(path: &Path) -> Result<U8Archive, Box<dyn Error>> {
+    let mut data = fs::read(path)?;
+    if path.extension().is_some_and(|ext| ext == "LZ") {
+        println!("Decompressing {}...", path.display());
+        data = lz11::decompress(&data)?;
+    }
+    U8Archive::from_file(&mut Cursor::new(data))
+}
+
+// Reads a bzs (ex: "dat/stage.bzs") from an archive. Missing or broken files only print a message
+fn read_archive_bzs(archive: &U8Archive, path: &str) -> Option<BZS> {
+    let data = archive.get_file(path)?;
+    match BZS::read(&mut Cursor::new(data)) {
+        Ok(bzs) => Some(bzs),
+        Err(e) => {
+            println!("Unable to read {path}: {e}");
+            None
+        }
+    }
+}
+
+// A short description of the contents of a bzs, shown when hovering a node
+fn bzs_summary(bzs: &BZS) -> String {
+    let objects = |layer: &LayerData| {
+        layer.sobj().len()
+            + layer.sobs().len()
+            + layer.obj().len()
+            + layer.objs().len()
+            + layer.stag().len()
+            + layer.stas().len()
+            + layer.sndt().len()
+            + layer.door().len()
+    };
+    let layer_objects: usize = bzs.layers().iter().map(objects).sum();
+    let mut summary = format!(
+        "Scenes: {}\nCameras: {}\nPaths: {}\nAreas: {}\nEvents: {}\nSpawns: {}\nObjects: {} (+{} in layers)",
+        bzs.scen().len(),
+        bzs.cam().len() + bzs.pcam().len(),
+        bzs.path().len() + bzs.spth().len(),
+        bzs.area().len(),
+        bzs.evnt().len(),
+        bzs.ply().len(),
+        objects(bzs.objects()),
+        layer_objects,
+    );
+
+    let unknown: Vec<String> = bzs
+        .unknown_chunks()
+        .iter()
+        .chain(bzs.objects().unknown_chunks())
+        .chain(bzs.layers().iter().flat_map(|l| l.unknown_chunks()))
+        .map(|chunk| String::from_utf8_lossy(&chunk.magic).to_string())
+        .collect();
+    if !unknown.is_empty() {
+        summary += &format!("\nUnknown Chunks: {}", unknown.join(", "));
+    }
+    summary
+}
+
+// Collects the files directly inside the given folders of an archive as (name, data)
+fn archive_files<'a>(archive: &'a U8Archive, folders: &[&str]) -> Vec<(&'a str, &'a [u8])> {
+    folders
+        .iter()
+        .filter_map(|&folder| archive.get_node(folder))
+        .flat_map(|folder| folder.children())
+        .filter_map(|node| Some((node.name(), node.data()?)))
+        .collect()
+}
+
+impl Scene {
+    // Builds the models for a list of archive files. Each kcl/dzb is paired with the plc sharing its stem
+    fn add_archive_models(&mut self, node: &mut SceneNode, files: &[(&str, &[u8])]) {
+        for &(file_name, data) in files {
+            let Some((stem, extension)) = file_name.rsplit_once('.') else {
+                continue;
+            };
+            if extension != "kcl" && extension != "dzb" {
+                continue;
+            }
+
+            // find plc
+            let plc_data = files
+                .iter()
+                .find(|&&(name, _)| name.rsplit_once('.') == Some((stem, "plc")))
+                .map(|&(_, data)| data);
+            let Some(plc_data) = plc_data else {
+                println!(
+                    "Not displaying {file_name}. Did not find a matching property (.plc) file"
+                );
+                continue;
+            };
+
+            if extension == "kcl" {
+                println!("{file_name}: Creating KCL Model");
+                match KCLModel::from_data(stem.to_string(), data, plc_data) {
+                    Ok(kcl_model) => {
+                        node.kcl_model_idx.push(self.kcl_models.len());
+                        self.kcl_models.push(kcl_model);
+                    }
+                    Err(e) => {
+                        println!("Unable to build KCLModel: {}", e);
+                    }
+                }
+            } else {
+                println!("{file_name}: Creating DZB Model");
+                match DZBModel::from_data(stem.to_string(), data, plc_data) {
+                    Ok(dzb_model) => {
+                        node.dzb_model_idx.push(self.dzb_models.len());
+                        self.dzb_models.push(dzb_model);
+                    }
+                    Err(e) => {
+                        println!("Unable to build DZBModel: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    // Builds a node per object archive (oarc) found in `archive`. Mirrors `Oarc/<Obj>` of the preprocessed layout
+    fn build_archive_objects(&mut self, name: String, archive: &U8Archive) -> Option<SceneNode> {
+        let mut node = SceneNode::default().with_name(name);
+
+        let objects = archive_files(archive, &["oarc"]);
+        for (arc_name, data) in objects {
+            let obj_arc = match U8Archive::from_file(&mut Cursor::new(data)) {
+                Ok(obj_arc) => obj_arc,
+                Err(e) => {
+                    println!("Unable to read {arc_name}: {e}");
+                    continue;
+                }
+            };
+
+            // Object collision is not kept in a consistent folder, so look at every file
+            let files: Vec<(String, &[u8])> = obj_arc.files().collect();
+            let files: Vec<(&str, &[u8])> = files
+                .iter()
+                .map(|(path, data)| (path.rsplit('/').next().unwrap(), *data))
+                .collect();
+
+            let obj_name = arc_name.strip_suffix(".arc").unwrap_or(arc_name);
+            let mut obj_node = SceneNode::default().with_name(obj_name.to_string());
+            obj_node.object_arc = true;
+            self.add_archive_models(&mut obj_node, &files);
+            if let Some(obj_node) = self.finish_node(obj_node) {
+                node.children.push(obj_node);
+            }
+        }
+
+        self.finish_node(node)
+    }
+
+    // Builds a scene from the layer 0 archive of a stage. Mirrors the preprocessed `<Stage>` layout
+    fn build_archive_stage(&mut self, name: String, stage_arc: &U8Archive) -> Option<SceneNode> {
+        let mut node = SceneNode::default().with_name(name);
+
+        // Stage collision is stored as dzb with the plc in the dat folder
+        let mut addon = SceneNode::default().with_name("addon".to_string());
+        self.add_archive_models(&mut addon, &archive_files(stage_arc, &["dat", "dzb"]));
+        if let Some(addon) = self.finish_node(addon) {
+            node.children.push(addon);
+        }
+
+        // Each room is its own archive (<Stage>_r##.arc) with kcl collision
+        let mut rooms = SceneNode::default().with_name("rooms".to_string());
+        for (arc_name, data) in archive_files(stage_arc, &["rarc"]) {
+            let room_arc = match U8Archive::from_file(&mut Cursor::new(data)) {
+                Ok(room_arc) => room_arc,
+                Err(e) => {
+                    println!("Unable to read {arc_name}: {e}");
+                    continue;
+                }
+            };
+
+            let stem = arc_name.strip_suffix(".arc").unwrap_or(arc_name);
+            let room_name = match stem.get(stem.len().saturating_sub(2)..) {
+                Some(id) if id.parse::<u32>().is_ok() => {
+                    format!("Room {}", id.parse::<u32>().unwrap())
+                }
+                _ => stem.to_string(),
+            };
+
+            let mut room = SceneNode::default().with_name(room_name);
+            self.add_archive_models(&mut room, &archive_files(&room_arc, &["dat", "kcl"]));
+            if let Some(bzs) = read_archive_bzs(&room_arc, "dat/room.bzs") {
+                self.add_bzs(&mut room, bzs);
+            }
+            if let Some(room) = self.finish_node(room) {
+                rooms.children.push(room);
+            }
+        }
+        if let Some(rooms) = self.finish_node(rooms) {
+            node.children.push(rooms);
+        }
+
+        // Objects that are shipped with the stage
+        if let Some(objects) = self.build_archive_objects("Oarc".to_string(), stage_arc) {
+            node.children.push(objects);
+        }
+
+        if let Some(bzs) = read_archive_bzs(stage_arc, "dat/stage.bzs") {
+            self.add_bzs(&mut node, bzs);
+        }
+        self.finish_node(node)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                          Object Collision Instancing                                              //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Name of the node holding the object archives of a stage
+const OBJECTS_NODE: &str = "Oarc";
+
+// Object archive whose collision can be placed at actors
+struct ObjectArchive {
+    name: String,
+    dzb_names: Vec<String>,    // Names of the collision files
+    dzb_model_idx: Vec<usize>, // Into dzb_models
+}
+
+// Finds the object archive holding the collision of an actor. Tries in order: an archive named after the actor, then
+//  an archive with a collision file named after the actor. When the archives are limited to the ones listed in ARCN
+//  (`arcs`), an archive whose name contains the actor name is tried last. Every archive is too loose for that
+fn find_object<'a>(
+    objects: &'a [ObjectArchive],
+    arcs: Option<&[String]>,
+    actor_name: &str,
+) -> Option<&'a ObjectArchive> {
+    let actor_name = actor_name.to_lowercase();
+    let candidates: Vec<&ObjectArchive> = objects
+        .iter()
+        .filter(|object| arcs.is_none_or(|arcs| arcs.contains(&object.name)))
+        .collect();
+    let exact = candidates
+        .iter()
+        .find(|object| object.name.to_lowercase() == actor_name)
+        .or_else(|| {
+            candidates.iter().find(|object| {
+                object
+                    .dzb_names
+                    .iter()
+                    .any(|dzb| dzb.to_lowercase() == actor_name)
+            })
+        });
+    if exact.is_some() || arcs.is_none() {
+        return exact.copied();
+    }
+
+    let matches: Vec<&ObjectArchive> = candidates
+        .into_iter()
+        .filter(|object| object.name.to_lowercase().contains(&actor_name))
+        .collect();
+    if matches.len() > 1 {
+        let names: Vec<&str> = matches.iter().map(|object| object.name.as_str()).collect();
+        println!(
+            "Actor {actor_name} matches the object archives {}. Using {}",
+            names.join(", "),
+            names[0]
+        );
+    }
+    matches.first().copied()
+}
+
+impl Scene {
+    // Loads the object archives used by the stage from a preprocessed `Oarc` folder
+    fn add_dir_objects(&mut self, oarc_dir: &Path) {
+        let mut names = Vec::new();
+        self.root_node.for_each_bzs(&mut |bzs| {
+            for layer_data in bzs.layers().iter().chain([bzs.objects()]) {
+                names.extend(layer_data.arcn().iter().map(|arcn| arcn.name.clone()));
+            }
+        });
+        names.extend(
+            self.actor_models
+                .iter()
+                .flat_map(|model| &model.actors)
+                .map(|actor| actor.name.clone()),
+        );
+        names.sort_unstable();
+        names.dedup();
+
+        let mut objects = SceneNode::default().with_name(OBJECTS_NODE.to_string());
+        for name in names {
+            let obj_dir = oarc_dir.join(&name);
+            if !obj_dir.is_dir() {
+                continue;
+            }
+            if let Some(mut obj_node) = self.build_scene(obj_dir) {
+                obj_node.object_arc = true;
+                objects.children.push(obj_node);
+            }
+        }
+        if let Some(objects) = self.finish_node(objects) {
+            if !objects.children.is_empty() {
+                self.root_node.children.push(objects);
+            }
+        }
+    }
+
+    // Places the collision of the object archives at every actor using them
+    fn instance_objects(&mut self) {
+        let objects: Vec<ObjectArchive> = self
+            .root_node
+            .children
+            .iter()
+            .filter(|node| node.name == OBJECTS_NODE)
+            .flat_map(|node| &node.children)
+            .filter(|node| !node.dzb_model_idx.is_empty())
+            .map(|node| {
+                let dzb_names = node
+                    .dzb_model_idx
+                    .iter()
+                    .map(|&i| self.dzb_models[i].name.clone())
+                    .collect();
+                ObjectArchive {
+                    name: node.name.clone(),
+                    dzb_names,
+                    dzb_model_idx: node.dzb_model_idx.clone(),
+                }
+            })
+            .collect();
+        if objects.is_empty() {
+            return;
+        }
+
+        let actor_models = &self.actor_models;
+        let mut instanced = false;
+        self.root_node.for_each_node_mut(&mut |node| {
+            let Some(bzs) = &node.bzs else {
+                return;
+            };
+            // Archives the game loads for each layer, only known when the bzs lists them
+            let layer_arcs = |layer: Option<usize>| -> Option<Vec<String>> {
+                let layers = [
+                    Some(bzs.objects()),
+                    bzs.layer(0),
+                    layer.and_then(|l| bzs.layer(l)),
+                ];
+                let arcs: Vec<String> = layers
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|layer_data| layer_data.arcn())
+                    .map(|arcn| arcn.name.clone())
+                    .collect();
+                Some(arcs).filter(|arcs| !arcs.is_empty())
+            };
+
+            let mut instances = Vec::new();
+            for &actor_model_idx in &node.actor_model_idx {
+                for (actor_idx, actor) in actor_models[actor_model_idx].actors.iter().enumerate() {
+                    let arcs = layer_arcs(actor.layer);
+                    if let Some(object) = find_object(&objects, arcs.as_deref(), &actor.name) {
+                        instances.push(ObjectInstance {
+                            actor_model_idx,
+                            actor_idx,
+                            dzb_model_idx: object.dzb_model_idx.clone(),
+                        });
+                    }
+                }
+            }
+            instanced |= !instances.is_empty();
+            node.object_instances = instances;
+        });
+
+        // The objects are now drawn where they are placed instead of at the origin
+        if instanced {
+            self.root_node
+                .children
+                .iter_mut()
+                .filter(|node| node.name == OBJECTS_NODE)
+                .for_each(|node| node.render = false);
+        }
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //                                            Outer Scene Interfacing                                                //
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+// Triangles matching the property filter are highlighted, the others are darkened
+const FILTER_MATCH_COLOR: Vec4 = Vec4::new(1.0, 0.45, 0.0, 1.0);
+const FILTER_DIM: f32 = 0.3;
+
 impl Scene {
     fn new() -> Self {
         Self {
             camera: Camera::new(),
             kcl_models: Vec::new(),
             dzb_models: Vec::new(),
+            actor_models: Vec::new(),
+            area_models: Vec::new(),
+            path_models: Vec::new(),
+            spawn_models: Vec::new(),
+            camera_models: Vec::new(),
+            filter: SceneFilter::new(),
+            story_state: StoryState::default(),
+            selected_room: None,
+            link_room_visibility: false,
+            event_table: EventTable::new(),
             model_mat: Mat4::IDENTITY,
             root_node: SceneNode::default(),
         }
@@ -198,18 +866,90 @@ impl Scene {
         // The following function loops through all directories and returns the graph of nodes
         let mut scene: Scene = Self::new();
 
-        scene.root_node = scene.build_scene(root_dir).expect("Could not build Scene");
+        scene.root_node = scene
+            .build_scene(root_dir.clone())
+            .expect("Could not build Scene");
+
+        // Object collision is shared by every stage in `<root>/../Oarc`
+        if let Some(oarc_dir) = root_dir.parent().map(|dir| dir.join("Oarc")) {
+            if oarc_dir != root_dir {
+                scene.add_dir_objects(&oarc_dir);
+            }
+        }
+        scene.instance_objects();
+
+        Ok(scene)
+    }
+
+    /// Builds a scene from a stage folder of a game extract (ex: `<romfs>/Stage/F000`)
+    pub fn from_game_extract(stage_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+        if !stage_dir.is_dir() {
+            return Err(SceneError::InvalidRoot(stage_dir).into());
+        }
+
+        let stage_name = stage_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| SceneError::InvalidName(stage_dir.clone()))?
+            .to_string();
+
+        // Only layer 0 holds collision
+        let arc_name = format!("{stage_name}_stg_l0.arc");
+        let arc_path = find_archive(&stage_dir, &arc_name)
+            .ok_or_else(|| SceneError::MissingArchive(stage_dir.clone(), arc_name))?;
+        let stage_arc = read_archive(&arc_path)?;
+
+        let mut scene: Scene = Self::new();
+
+        scene.root_node = scene
+            .build_archive_stage(stage_name.clone(), &stage_arc)
+            .ok_or(SceneError::EmptyArchive(stage_name))?;
+        scene.instance_objects();
+
+        Ok(scene)
+    }
+
+    /// Builds a scene of the shared objects in `<extract>/Object/ObjectPack.arc`
+    pub fn from_object_pack(extract_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+        let object_dir = extract_dir.join("Object");
+        let arc_name = "ObjectPack.arc".to_string();
+        let arc_path = find_archive(&object_dir, &arc_name)
+            .ok_or_else(|| SceneError::MissingArchive(object_dir.clone(), arc_name))?;
+        let object_pack = read_archive(&arc_path)?;
+
+        let mut scene: Scene = Self::new();
+
+        scene.root_node = scene
+            .build_archive_objects("ObjectPack".to_string(), &object_pack)
+            .ok_or_else(|| SceneError::EmptyArchive("ObjectPack".to_string()))?;
 
         Ok(scene)
     }
 
-    pub fn update_scene_property_filter(&mut self, property_entry: usize, range_selection: u32) {
+    /// Colors the triangles by `property_field`. When there is a filter, the matching triangles are highlighted
+    ///  and the number of matches is returned
+    pub fn update_scene_property_filter(
+        &mut self,
+        property_field: Option<&PLCField>,
+        isolated_value: Option<u32>,
+        property_filter: Option<&PLCFilter>,
+    ) -> usize {
+        let mut matches = 0;
+        let mut filter_color = |prop: &PLCEntry, clr: Vec4| match property_filter {
+            Some(filter) if filter.matches(prop) => {
+                matches += 1;
+                FILTER_MATCH_COLOR
+            }
+            Some(_) => (clr * FILTER_DIM).with_w(1.0),
+            None => clr,
+        };
+
         self.kcl_models.iter_mut().for_each(|model| {
             model.properties.iter().enumerate().for_each(|(i, prop)| {
                 let clr = prop
-                    .get_color(property_entry, range_selection)
+                    .get_color(property_field, isolated_value)
                     .unwrap_or_else(|| {
-                        let v1 = model.verts[i * 3 + 0].pos;
+                        let v1 = model.verts[i * 3].pos;
                         let v2 = model.verts[i * 3 + 1].pos;
                         let v3 = model.verts[i * 3 + 2].pos;
                         (v2 - v1)
@@ -219,7 +959,8 @@ impl Scene {
                             .xyzx()
                             .with_w(1.0)
                     });
-                model.verts[i * 3 + 0].clr = clr;
+                let clr = filter_color(prop, clr);
+                model.verts[i * 3].clr = clr;
                 model.verts[i * 3 + 1].clr = clr;
                 model.verts[i * 3 + 2].clr = clr;
             });
@@ -227,9 +968,9 @@ impl Scene {
         self.dzb_models.iter_mut().for_each(|model| {
             model.properties.iter().enumerate().for_each(|(i, prop)| {
                 let clr = prop
-                    .get_color(property_entry, range_selection)
+                    .get_color(property_field, isolated_value)
                     .unwrap_or_else(|| {
-                        let v1 = model.verts[i * 3 + 0].pos;
+                        let v1 = model.verts[i * 3].pos;
                         let v2 = model.verts[i * 3 + 1].pos;
                         let v3 = model.verts[i * 3 + 2].pos;
                         (v2 - v1)
@@ -239,16 +980,63 @@ impl Scene {
                             .xyzx()
                             .with_w(1.0)
                     });
-                model.verts[i * 3 + 0].clr = clr;
+                let clr = filter_color(prop, clr);
+                model.verts[i * 3].clr = clr;
                 model.verts[i * 3 + 1].clr = clr;
                 model.verts[i * 3 + 2].clr = clr;
             });
         });
+        matches
+    }
+
+    /// Number of triangles with each value of `field`, for each collision model
+    pub fn property_histogram(&self, field: &PLCField) -> Vec<(String, BTreeMap<u32, usize>)> {
+        let count = |name: &String, props: &Vec<PLCEntry>| {
+            let mut values = BTreeMap::new();
+            props.iter().for_each(|prop| {
+                *values.entry(field.get(prop)).or_insert(0) += 1;
+            });
+            (name.clone(), values)
+        };
+        let kcl_counts = self
+            .kcl_models
+            .iter()
+            .map(|model| count(&model.name, &model.properties));
+        let dzb_counts = self
+            .dzb_models
+            .iter()
+            .map(|model| count(&model.name, &model.properties));
+        kcl_counts.chain(dzb_counts).collect()
     }
 
     pub fn get_root_name(&self) -> String {
         self.root_node.name.clone()
     }
+
+    /// Every exit (SCEN) of the stage and its rooms
+    pub fn exits(&self) -> Vec<StageExit> {
+        let mut exits = Vec::new();
+        self.root_node.for_each_named_bzs(&mut |source, bzs| {
+            exits.extend(bzs.scen().iter().enumerate().map(|(i, scen)| {
+                StageExit::new(self.root_node.name.clone(), source.to_string(), i, scen)
+            }));
+        });
+        exits
+    }
+
+    /// Every event (EVNT) of the stage and its rooms
+    pub fn events(&self) -> Vec<Event> {
+        let mut events = Vec::new();
+        self.root_node.for_each_named_bzs(&mut |source, bzs| {
+            events.extend(
+                bzs.evnt()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, evnt)| Event::new(source.to_string(), i, evnt)),
+            );
+        });
+        events
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -263,16 +1051,27 @@ impl SceneNode {
 
         kcl_models: &mut Vec<KCLModel>,
         dzb_models: &mut Vec<DZBModel>,
+        actor_models: &mut Vec<ActorModel>,
+        filter: &SceneFilter,
     ) {
-        if self.render {
+        if self.object_arc && !filter.shows_object(&self.name) {
+            return;
+        }
+        if self.visible() {
             self.kcl_model_idx.iter().for_each(|&index| {
                 kcl_models.get_mut(index).unwrap().draw(gl, shader);
             });
             self.dzb_model_idx.iter().for_each(|&index| {
                 dzb_models.get_mut(index).unwrap().draw(gl, shader);
             });
+            self.actor_model_idx.iter().for_each(|&index| {
+                actor_models
+                    .get_mut(index)
+                    .unwrap()
+                    .draw_actors(gl, |actor| filter.shows_actor(actor));
+            });
             self.children.iter().for_each(|node| {
-                node.draw(gl, shader, kcl_models, dzb_models);
+                node.draw(gl, shader, kcl_models, dzb_models, actor_models, filter);
             });
         }
     }
@@ -286,6 +1085,21 @@ impl Model for Scene {
         self.dzb_models
             .iter_mut()
             .for_each(|model| model.setup_gl(gl));
+        self.actor_models
+            .iter_mut()
+            .for_each(|model| model.setup_gl(gl));
+        self.area_models
+            .iter_mut()
+            .for_each(|model| model.setup_gl(gl));
+        self.path_models
+            .iter_mut()
+            .for_each(|model| model.setup_gl(gl));
+        self.spawn_models
+            .iter_mut()
+            .for_each(|model| model.setup_gl(gl));
+        self.camera_models
+            .iter_mut()
+            .for_each(|model| model.setup_gl(gl));
     }
 
     fn destroy_gl(&mut self, gl: &glow::Context) {
@@ -295,6 +1109,21 @@ impl Model for Scene {
         self.dzb_models
             .iter_mut()
             .for_each(|model| model.destroy_gl(gl));
+        self.actor_models
+            .iter_mut()
+            .for_each(|model| model.destroy_gl(gl));
+        self.area_models
+            .iter_mut()
+            .for_each(|model| model.destroy_gl(gl));
+        self.path_models
+            .iter_mut()
+            .for_each(|model| model.destroy_gl(gl));
+        self.spawn_models
+            .iter_mut()
+            .for_each(|model| model.destroy_gl(gl));
+        self.camera_models
+            .iter_mut()
+            .for_each(|model| model.destroy_gl(gl));
     }
 
     fn update_gl(&mut self, gl: &glow::Context) {
@@ -304,6 +1133,21 @@ impl Model for Scene {
         self.dzb_models
             .iter_mut()
             .for_each(|model| model.update_gl(gl));
+        self.actor_models
+            .iter_mut()
+            .for_each(|model| model.update_gl(gl));
+        self.area_models
+            .iter_mut()
+            .for_each(|model| model.update_gl(gl));
+        self.path_models
+            .iter_mut()
+            .for_each(|model| model.update_gl(gl));
+        self.spawn_models
+            .iter_mut()
+            .for_each(|model| model.update_gl(gl));
+        self.camera_models
+            .iter_mut()
+            .for_each(|model| model.update_gl(gl));
     }
 
     fn draw(&mut self, gl: &glow::Context, shader: &crate::gfx::Shader) {
@@ -312,8 +1156,71 @@ impl Model for Scene {
         shader.set_uniform(gl, "view", ShaderUniformTypes::Mat4(&self.camera.get_mtx()));
         shader.set_uniform(gl, "model", ShaderUniformTypes::Mat4(&self.model_mat));
 
-        self.root_node
-            .draw(gl, shader, &mut self.kcl_models, &mut self.dzb_models);
+        self.root_node.draw(
+            gl,
+            shader,
+            &mut self.kcl_models,
+            &mut self.dzb_models,
+            &mut self.actor_models,
+            &self.filter,
+        );
+
+        let mut nodes = Vec::new();
+        self.root_node.rendered_nodes(&mut nodes);
+
+        if self.filter.object_render {
+            for instance in nodes.iter().flat_map(|node| &node.object_instances) {
+                let actor = &self.actor_models[instance.actor_model_idx].actors[instance.actor_idx];
+                if !self.filter.shows_actor(actor) {
+                    continue;
+                }
+                let model_mat = self.model_mat * actor.object_transform();
+                shader.set_uniform(gl, "model", ShaderUniformTypes::Mat4(&model_mat));
+                instance
+                    .dzb_model_idx
+                    .iter()
+                    .for_each(|&index| self.dzb_models[index].draw(gl, shader));
+            }
+            shader.set_uniform(gl, "model", ShaderUniformTypes::Mat4(&self.model_mat));
+        }
+
+        if self.filter.path_render {
+            nodes
+                .iter()
+                .flat_map(|node| &node.path_model_idx)
+                .for_each(|&index| self.path_models[index].draw(gl, shader));
+        }
+        if self.filter.spawn_render {
+            nodes
+                .iter()
+                .flat_map(|node| &node.spawn_model_idx)
+                .for_each(|&index| self.spawn_models[index].draw(gl, shader));
+        }
+        if self.filter.camera_render {
+            nodes
+                .iter()
+                .flat_map(|node| &node.camera_model_idx)
+                .for_each(|&index| self.camera_models[index].draw(gl, shader));
+        }
+
+        // Areas are see through, so they are drawn last without writing depth
+        if self.filter.area_render {
+            unsafe {
+                use glow::HasContext as _;
+                gl.enable(glow::BLEND);
+                gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+                gl.depth_mask(false);
+            }
+            nodes
+                .iter()
+                .flat_map(|node| &node.area_model_idx)
+                .for_each(|&index| self.area_models[index].draw(gl, shader));
+            unsafe {
+                use glow::HasContext as _;
+                gl.depth_mask(true);
+                gl.disable(glow::BLEND);
+            }
+        }
     }
 }
 
@@ -322,15 +1229,44 @@ impl Model for Scene {
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 impl SceneNode {
+    #[allow(clippy::too_many_arguments)]
     fn visibility_ui(
         &mut self,
         kcl_models: &mut Vec<KCLModel>,
         dzb_models: &mut Vec<DZBModel>,
+        actor_models: &mut Vec<ActorModel>,
+        area_models: &mut Vec<AreaModel>,
+        path_models: &mut Vec<PathModel>,
+        spawn_models: &mut Vec<SpawnModel>,
+        camera_models: &mut Vec<CameraModel>,
         ui: &mut egui::Ui,
         show_name: bool,
-    ) {
+    ) -> Option<usize> {
+        // Room whose links were clicked
+        let mut clicked_room = None;
         if show_name {
-            ui.checkbox(&mut self.render, self.name.clone());
+            let name = egui::RichText::new(self.name.clone());
+            let name = match self.room_state {
+                RoomState::Unrelated if self.link_hidden => name.weak(),
+                RoomState::Unrelated => name,
+                RoomState::Selected => name.strong().color(egui::Color32::YELLOW),
+                RoomState::Linked => name.color(egui::Color32::LIGHT_GREEN),
+            };
+            ui.horizontal(|ui| {
+                let response = ui.checkbox(&mut self.render, name);
+                if let Some(bzs) = &self.bzs {
+                    response.on_hover_text(bzs_summary(bzs));
+                }
+                if let Some(room) = self.room_id() {
+                    if ui
+                        .small_button("Links")
+                        .on_hover_text("Highlight the rooms loaded with this room")
+                        .clicked()
+                    {
+                        clicked_room = Some(room);
+                    }
+                }
+            });
         }
         if self.render {
             ui.indent(self.name.clone(), |ui| {
@@ -341,19 +1277,810 @@ impl SceneNode {
                 self.dzb_model_idx.iter().for_each(|&model| {
                     let model = &mut dzb_models[model];
                     ui.checkbox(&mut model.render, model.name.clone());
+                    if model.render && !model.groups.is_empty() {
+                        let id = (model.name.clone(), "groups");
+                        ui.indent(id, |ui| model.groups_ui(ui));
+                    }
+                });
+                self.actor_model_idx.iter().for_each(|&model| {
+                    let model = &mut actor_models[model];
+                    ui.checkbox(&mut model.render, model.name.clone());
+                });
+                self.area_model_idx.iter().for_each(|&model| {
+                    let model = &mut area_models[model];
+                    ui.checkbox(&mut model.render, model.name.clone());
+                });
+                self.path_model_idx.iter().for_each(|&model| {
+                    let model = &mut path_models[model];
+                    ui.checkbox(&mut model.render, model.name.clone());
+                });
+                self.spawn_model_idx.iter().for_each(|&model| {
+                    let model = &mut spawn_models[model];
+                    ui.checkbox(&mut model.render, model.name.clone());
+                });
+                self.camera_model_idx.iter().for_each(|&model| {
+                    let model = &mut camera_models[model];
+                    ui.checkbox(&mut model.render, model.name.clone());
                 });
 
-                self.children.iter_mut().for_each(|node| {
-                    node.visibility_ui(kcl_models, dzb_models, ui, true);
-                })
+                for node in self.children.iter_mut() {
+                    clicked_room = node
+                        .visibility_ui(
+                            kcl_models,
+                            dzb_models,
+                            actor_models,
+                            area_models,
+                            path_models,
+                            spawn_models,
+                            camera_models,
+                            ui,
+                            true,
+                        )
+                        .or(clicked_room);
+                }
             });
         }
+        clicked_room
     }
 }
 
+// Offset of the camera from a point it is moved to
+const TELEPORT_DISTANCE: f32 = 600.0;
+const TELEPORT_HEIGHT: f32 = 300.0;
+
 impl Scene {
+    // Selects the layer whose actors and objects are shown. Layer 0 is always shown with it
+    fn set_layer(&mut self, layer: Option<usize>) {
+        self.filter.layer = layer;
+
+        // Only filter objects when the stage data lists the archives of its layers
+        let mut has_arcn = false;
+        let mut loaded_arcs = Vec::new();
+        self.root_node.for_each_bzs(&mut |bzs| {
+            let layers = [
+                Some(bzs.objects()),
+                bzs.layer(0),
+                layer.and_then(|l| bzs.layer(l)),
+            ];
+            for layer_data in bzs.layers().iter().chain([bzs.objects()]) {
+                has_arcn |= !layer_data.arcn().is_empty();
+            }
+            for layer_data in layers.into_iter().flatten() {
+                loaded_arcs.extend(layer_data.arcn().iter().map(|arcn| arcn.name.clone()));
+            }
+        });
+
+        self.filter.loaded_arcs = match layer {
+            Some(_) if has_arcn => Some(loaded_arcs),
+            _ => None,
+        };
+    }
+
+    fn layer_ui(&mut self, ui: &mut egui::Ui) {
+        let mut bzs_list = Vec::new();
+        self.root_node.for_each_bzs(&mut |bzs| bzs_list.push(bzs));
+
+        // Number of actors in each layer to help finding the used ones
+        let mut layer_actors = [0usize; LAYER_COUNT];
+        self.actor_models
+            .iter()
+            .flat_map(|model| &model.actors)
+            .filter_map(|actor| actor.layer)
+            .for_each(|layer| layer_actors[layer] += 1);
+
+        let layer_text = |layer: Option<usize>| match layer {
+            Some(layer) => format!("Layer {layer} ({} actors)", layer_actors[layer]),
+            None => "All Layers".to_string(),
+        };
+
+        let mut selected = self.filter.layer;
+        egui::ComboBox::from_label("Layer")
+            .selected_text(layer_text(selected))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, layer_text(None));
+                for layer in 0..layer_actors.len() {
+                    ui.selectable_value(&mut selected, Some(layer), layer_text(Some(layer)));
+                }
+            });
+
+        // The rules the game uses to pick a layer
+        let lyse: Vec<_> = bzs_list.iter().flat_map(|bzs| bzs.lyse()).collect();
+        let lylt: Vec<_> = bzs_list.iter().flat_map(|bzs| bzs.lylt()).collect();
+        if !lyse.is_empty() || !lylt.is_empty() {
+            self.layer_rules_ui(ui, &lyse, &lylt);
+        }
+        if !lyse.is_empty() && Self::story_state_ui(ui, &mut self.story_state, &lyse) {
+            // Layer 0 is loaded when no rule applies
+            selected = Some(self.story_state.layer(&lyse).unwrap_or(0));
+        }
+
+        if selected != self.filter.layer {
+            self.set_layer(selected);
+        }
+    }
+
+    // Returns true when the story state was changed
+    fn story_state_ui(ui: &mut egui::Ui, story_state: &mut StoryState, lyse: &[&LYSE]) -> bool {
+        let mut changed = false;
+        ui.collapsing("Story Flag Simulator", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Story Flags");
+                changed |= ui
+                    .text_edit_singleline(&mut story_state.story_flags)
+                    .on_hover_text("Active story flags, separated by commas or spaces")
+                    .changed();
+            });
+            changed |= ui.checkbox(&mut story_state.night, "Night").changed();
+            ui.horizontal(|ui| {
+                ui.label("When several rules apply, use the")
+                    .on_hover_text("Which rule the game picks is not confirmed");
+                changed |= ui
+                    .radio_value(&mut story_state.order, LyseOrder::FirstMatch, "First")
+                    .changed();
+                changed |= ui
+                    .radio_value(&mut story_state.order, LyseOrder::LastMatch, "Last")
+                    .changed();
+            });
+
+            let matching = story_state.matching(lyse);
+            if matching.len() > 1 {
+                let layers: Vec<_> = matching
+                    .iter()
+                    .map(|entry| entry.layer.to_string())
+                    .collect();
+                ui.label(format!(
+                    "{} rules apply (layers {})",
+                    matching.len(),
+                    layers.join(", ")
+                ));
+            }
+            match story_state.layer(lyse) {
+                Some(layer) => ui.label(format!("The game loads layer {layer}")),
+                None => ui.label("No rule applies, the game loads layer 0"),
+            };
+        });
+        changed
+    }
+
+    fn layer_rules_ui(&self, ui: &mut egui::Ui, lyse: &[&LYSE], lylt: &[&LYLT]) {
+        ui.collapsing("Layer Rules", |ui| {
+            let highlight = |ui: &mut egui::Ui, layer: u8, text: String| {
+                if Some(layer as usize) == self.filter.layer {
+                    ui.strong(text);
+                } else {
+                    ui.label(text);
+                }
+            };
+            if !lyse.is_empty() {
+                ui.label("LYSE (Story Flag -> Layer)");
+                egui::Grid::new("LYSE").striped(true).show(ui, |ui| {
+                    ui.label("Story Flag");
+                    ui.label("Night");
+                    ui.label("Layer");
+                    ui.end_row();
+                    for entry in lyse {
+                        ui.label(entry.story_flag.to_string());
+                        ui.label(entry.night.to_string());
+                        highlight(ui, entry.layer, entry.layer.to_string());
+                        ui.end_row();
+                    }
+                });
+            }
+            if !lylt.is_empty() {
+                ui.label("LYLT (Layer -> Demo)");
+                egui::Grid::new("LYLT").striped(true).show(ui, |ui| {
+                    ui.label("Layer");
+                    ui.label("Demo High");
+                    ui.label("Demo Low");
+                    ui.end_row();
+                    for entry in lylt {
+                        highlight(ui, entry.layer, entry.layer.to_string());
+                        ui.label(entry.demo_high.to_string());
+                        ui.label(entry.demo_low.to_string());
+                        ui.end_row();
+                    }
+                });
+            }
+        });
+    }
+
+    // Lists the raw bytes of every path, their meaning is not known yet
+    fn paths_ui(&self, ui: &mut egui::Ui) {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{b:02X}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        ui.collapsing("Path Data", |ui| {
+            for (i, model) in self.path_models.iter().enumerate() {
+                egui::Grid::new(("Paths", i)).striped(true).show(ui, |ui| {
+                    ui.label("Path");
+                    ui.label("Points");
+                    ui.label("unk1");
+                    ui.label("unk2");
+                    ui.label("unk3");
+                    ui.end_row();
+                    for path in &model.paths {
+                        ui.label(format!("{} {}", path.path_type.name(), path.index));
+                        let points = match (path.points.first(), path.points.last()) {
+                            (Some(first), Some(last)) => {
+                                format!("{}..={}", first.index, last.index)
+                            }
+                            _ => "None".to_string(),
+                        };
+                        // The raw bytes of each point are shown on hover
+                        let point_unks: Vec<String> = path
+                            .points
+                            .iter()
+                            .map(|point| format!("{}: {}", point.index, hex(&point.unk)))
+                            .collect();
+                        ui.label(points).on_hover_text(point_unks.join("\n"));
+                        ui.monospace(format!("{:02X}", path.unk1));
+                        ui.monospace(format!("{:02X}", path.unk2));
+                        ui.monospace(hex(&path.unk3));
+                        ui.end_row();
+                    }
+                });
+            }
+        });
+    }
+
+    // Lists every spawn, clicking one moves the camera to it
+    fn spawns_ui(&mut self, ui: &mut egui::Ui) {
+        let mut selected = None;
+        ui.collapsing("Spawn List", |ui| {
+            for spawn in self.spawn_models.iter().flat_map(|model| &model.spawns) {
+                if ui
+                    .button(spawn.label())
+                    .on_hover_text(spawn.tooltip())
+                    .clicked()
+                {
+                    selected = Some(spawn.clone());
+                }
+            }
+        });
+
+        if let Some(spawn) = selected {
+            self.teleport_to(spawn.pos, spawn.forward());
+        }
+    }
+
+    // Places the camera behind and above `pos`, looking at it along `forward`
+    fn teleport_to(&mut self, pos: Vec3, forward: Vec3) {
+        let pos = self.model_mat.transform_point3(pos);
+        let forward = self
+            .model_mat
+            .transform_vector3(forward)
+            .normalize_or(Vec3::Z);
+        self.camera
+            .set_pos(pos - forward * TELEPORT_DISTANCE + Vec3::Y * TELEPORT_HEIGHT);
+        self.camera.look_at(pos);
+    }
+
+    // Lists every CAM and PCAM with a button to view the scene through them
+    fn cameras_ui(&mut self, ui: &mut egui::Ui) {
+        // (eye, direction)
+        let mut selected = None;
+        ui.collapsing("Camera List", |ui| {
+            ui.weak(
+                "Directions are assumed: CAM angle as a yaw in degrees, PCAM as eye and target",
+            );
+            egui::Grid::new("Cameras").striped(true).show(ui, |ui| {
+                let cameras = self.camera_models.iter().flat_map(|model| &model.cameras);
+                for cam in cameras {
+                    ui.label(cam.label()).on_hover_text(cam.tooltip());
+                    if ui.button("Look Through").clicked() {
+                        selected = Some((cam.pos, cam.forward()));
+                    }
+                    ui.end_row();
+                }
+                let point_cameras = self
+                    .camera_models
+                    .iter()
+                    .flat_map(|model| &model.point_cameras);
+                for pcam in point_cameras {
+                    ui.label(pcam.label()).on_hover_text(pcam.tooltip());
+                    if ui.button("Look Through").clicked() {
+                        selected = Some((pcam.pos1, pcam.pos2 - pcam.pos1));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        if let Some((pos, dir)) = selected {
+            self.look_through(pos, dir);
+        }
+    }
+
+    // Places the camera exactly at `pos`, looking along `dir`
+    fn look_through(&mut self, pos: Vec3, dir: Vec3) {
+        let pos = self.model_mat.transform_point3(pos);
+        let dir = self.model_mat.transform_vector3(dir);
+        self.camera.set_pos(pos);
+        self.camera.look_at(pos + dir);
+    }
+
+    /// Rooms listed in RMPL as (room, rooms loaded with it)
+    pub fn room_links(&self) -> Vec<(usize, Vec<usize>)> {
+        let mut links = Vec::new();
+        self.root_node.for_each_bzs(&mut |bzs| {
+            links.extend(bzs.rmpl().iter().map(|rmpl| {
+                let rooms = rmpl.data.iter().map(|data| data.data as usize).collect();
+                (rmpl.id as usize, rooms)
+            }));
+        });
+        links
+    }
+
+    // Highlights the selected room and the rooms linked to it in the scene tree
+    fn select_room(&mut self, room: Option<usize>) {
+        self.selected_room = room;
+        let linked: Vec<usize> = self
+            .room_links()
+            .into_iter()
+            .filter(|(id, _)| Some(*id) == room)
+            .flat_map(|(_, rooms)| rooms)
+            .collect();
+
+        let link_visibility = self.link_room_visibility && room.is_some();
+        self.root_node.for_each_node_mut(&mut |node| {
+            let Some(id) = node.room_id() else {
+                return;
+            };
+            node.room_state = if Some(id) == room {
+                RoomState::Selected
+            } else if linked.contains(&id) {
+                RoomState::Linked
+            } else {
+                RoomState::Unrelated
+            };
+            node.link_hidden = link_visibility && node.room_state == RoomState::Unrelated;
+        });
+    }
+
+    fn room_links_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.collapsing("Room Links", |ui| {
+            changed |= ui
+                .checkbox(&mut self.link_room_visibility, "Only Show Linked Rooms")
+                .on_hover_text("Selecting a room only renders the rooms the game loads with it")
+                .changed();
+            let mut selected = self.selected_room;
+            egui::Grid::new("Room Links").striped(true).show(ui, |ui| {
+                for (room, rooms) in self.room_links() {
+                    ui.selectable_value(&mut selected, Some(room), format!("Room {room}"));
+                    let rooms: Vec<String> = rooms.iter().map(|room| room.to_string()).collect();
+                    ui.label(rooms.join(", "));
+                    ui.end_row();
+                }
+            });
+            changed |= selected != self.selected_room;
+            self.selected_room = selected;
+        });
+        if changed {
+            self.select_room(self.selected_room);
+        }
+    }
+
+    // Lists where every exit of the stage leads to
+    fn exits_ui(&self, ui: &mut egui::Ui, exits: &[StageExit]) {
+        ui.collapsing(format!("Exits ({})", exits.len()), |ui| {
+            egui::Grid::new("Exits").striped(true).show(ui, |ui| {
+                for header in ["Source", "#", "Stage", "Room", "Layer", "Entrance", "Night"] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for exit in exits {
+                    ui.label(&exit.source);
+                    ui.label(exit.index.to_string());
+                    ui.label(&exit.target_stage).on_hover_text(format!(
+                        "byte5: 0x{:02X}\nflag6: 0x{:02X}\nsave_prompt: {}",
+                        exit.byte5, exit.flag6, exit.save_prompt
+                    ));
+                    ui.label(exit.room.to_string());
+                    ui.label(exit.layer.to_string());
+                    ui.label(exit.entrance.to_string());
+                    ui.label(exit.night.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
     pub fn visibility_ui(&mut self, ui: &mut egui::Ui) {
+        if !self.actor_models.is_empty() {
+            self.layer_ui(ui);
+
+            ui.collapsing("Actor Types", |ui| {
+                ActorType::ALL.iter().for_each(|&actor_type| {
+                    let count: usize = self
+                        .actor_models
+                        .iter()
+                        .map(|model| {
+                            model
+                                .actors
+                                .iter()
+                                .filter(|actor| actor.actor_type == actor_type)
+                                .count()
+                        })
+                        .sum();
+                    ui.checkbox(
+                        &mut self.filter.actor_type_render[actor_type as usize],
+                        format!("{} ({count})", actor_type.name()),
+                    );
+                });
+            });
+        }
+        let mut instances = 0;
         self.root_node
-            .visibility_ui(&mut self.kcl_models, &mut self.dzb_models, ui, false);
+            .for_each_node_mut(&mut |node| instances += node.object_instances.len());
+        if instances > 0 {
+            ui.checkbox(
+                &mut self.filter.object_render,
+                format!("Object Collision ({instances})"),
+            )
+            .on_hover_text("Collision of the object archives placed at their actors");
+        }
+        if !self.area_models.is_empty() {
+            let count: usize = self.area_models.iter().map(|m| m.areas.len()).sum();
+            ui.checkbox(&mut self.filter.area_render, format!("Areas ({count})"));
+        }
+        if !self.path_models.is_empty() {
+            let count: usize = self.path_models.iter().map(|m| m.paths.len()).sum();
+            ui.checkbox(&mut self.filter.path_render, format!("Paths ({count})"));
+            ui.checkbox(&mut self.filter.point_labels, "Point Indices");
+            self.paths_ui(ui);
+        }
+        if !self.spawn_models.is_empty() {
+            let count: usize = self.spawn_models.iter().map(|m| m.spawns.len()).sum();
+            ui.checkbox(&mut self.filter.spawn_render, format!("Spawns ({count})"));
+            self.spawns_ui(ui);
+        }
+        if !self.camera_models.is_empty() {
+            let count: usize = self
+                .camera_models
+                .iter()
+                .map(|m| m.cameras.len() + m.point_cameras.len())
+                .sum();
+            ui.checkbox(&mut self.filter.camera_render, format!("Cameras ({count})"));
+            self.cameras_ui(ui);
+        }
+        let exits = self.exits();
+        if !exits.is_empty() {
+            self.exits_ui(ui, &exits);
+        }
+        let events = self.events();
+        if !events.is_empty() {
+            ui.collapsing(format!("Events ({})", events.len()), |ui| {
+                self.event_table.ui(ui, &events);
+            });
+        }
+
+        if !self.room_links().is_empty() {
+            self.room_links_ui(ui);
+        }
+
+        let clicked_room = self.root_node.visibility_ui(
+            &mut self.kcl_models,
+            &mut self.dzb_models,
+            &mut self.actor_models,
+            &mut self.area_models,
+            &mut self.path_models,
+            &mut self.spawn_models,
+            &mut self.camera_models,
+            ui,
+            false,
+        );
+        if let Some(room) = clicked_room {
+            // Clicking the selected room again clears the selection
+            let room = Some(room).filter(|&room| Some(room) != self.selected_room);
+            self.select_room(room);
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                             Screen Space Overlays                                                 //
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Pixels from a projected point that still count as hovering it
+const HOVER_DISTANCE: f32 = 12.0;
+
+// Projects a point to the screen. Returns the position and the distance to the camera, None when behind it
+fn project_to_screen(mvp: &Mat4, rect: egui::Rect, point: Vec3) -> Option<(egui::Pos2, f32)> {
+    let clip = *mvp * point.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    let screen = egui::pos2(
+        rect.left() + (ndc.x + 1.0) * 0.5 * rect.width(),
+        rect.top() + (1.0 - ndc.y) * 0.5 * rect.height(),
+    );
+    Some((screen, clip.w))
+}
+
+impl Scene {
+    fn mvp(&mut self, proj: &Mat4) -> Mat4 {
+        *proj * self.camera.get_mtx() * self.model_mat
+    }
+
+    /// The description of the rendered actor closest to `pointer` (in screen space), if one is near enough
+    pub fn actor_tooltip(
+        &mut self,
+        proj: &Mat4,
+        rect: egui::Rect,
+        pointer: egui::Pos2,
+    ) -> Option<String> {
+        let mvp = self.mvp(proj);
+
+        let mut nodes = Vec::new();
+        self.root_node.rendered_nodes(&mut nodes);
+
+        let mut closest: Option<(f32, String)> = None;
+        for actor in nodes
+            .iter()
+            .flat_map(|node| &node.actor_model_idx)
+            .map(|&index| &self.actor_models[index])
+            .filter(|model| model.render)
+            .flat_map(|model| &model.actors)
+            .filter(|actor| self.filter.shows_actor(actor))
+        {
+            let Some((screen, depth)) = project_to_screen(&mvp, rect, actor.center()) else {
+                continue;
+            };
+
+            // Prefer the actor closest to the camera
+            if screen.distance(pointer) <= HOVER_DISTANCE
+                && closest.as_ref().is_none_or(|(d, _)| depth < *d)
+            {
+                closest = Some((depth, actor.tooltip()));
+            }
+        }
+        closest.map(|(_, tooltip)| tooltip)
+    }
+
+    /// The labels of every rendered area as (screen position, text, highlighted).
+    ///  Hovering a label highlights the area and the areas linked to it
+    pub fn area_labels(
+        &mut self,
+        proj: &Mat4,
+        rect: egui::Rect,
+        pointer: Option<egui::Pos2>,
+    ) -> Vec<(egui::Pos2, String, bool)> {
+        let mvp = self.mvp(proj);
+
+        let mut nodes = Vec::new();
+        self.root_node.rendered_nodes(&mut nodes);
+        let mut models: Vec<usize> = nodes
+            .iter()
+            .flat_map(|node| node.area_model_idx.iter().copied())
+            .filter(|&index| self.area_models[index].render)
+            .collect();
+        if !self.filter.area_render {
+            models.clear();
+        }
+
+        // (model, area, screen position, depth)
+        let mut labels = Vec::new();
+        for &model in &models {
+            for (i, area) in self.area_models[model].areas.iter().enumerate() {
+                if let Some((screen, depth)) = project_to_screen(&mvp, rect, area.pos) {
+                    labels.push((model, i, screen, depth));
+                }
+            }
+        }
+
+        // Only the closest label under the pointer is hovered
+        let hovered = pointer.and_then(|pointer| {
+            labels
+                .iter()
+                .filter(|label| label.2.distance(pointer) <= HOVER_DISTANCE)
+                .min_by(|a, b| a.3.total_cmp(&b.3))
+                .map(|label| (label.0, label.1))
+        });
+        self.area_models
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, model)| {
+                model.highlight(hovered.filter(|h| h.0 == i).map(|h| h.1));
+            });
+
+        labels
+            .into_iter()
+            .map(|(model, i, screen, _)| {
+                let model = &self.area_models[model];
+                (screen, model.areas[i].label(), model.highlighted[i])
+            })
+            .collect()
+    }
+
+    /// The labels of path points, spawns and cameras as (screen position, text, color)
+    pub fn marker_labels(
+        &mut self,
+        proj: &Mat4,
+        rect: egui::Rect,
+    ) -> Vec<(egui::Pos2, String, egui::Color32)> {
+        let mvp = self.mvp(proj);
+
+        let mut nodes = Vec::new();
+        self.root_node.rendered_nodes(&mut nodes);
+
+        let mut labels = Vec::new();
+        let mut push_label = |pos: Vec3, text: String, color: egui::Color32| {
+            if let Some((screen, _)) = project_to_screen(&mvp, rect, pos) {
+                labels.push((screen, text, color));
+            }
+        };
+
+        if self.filter.path_render && self.filter.point_labels {
+            nodes
+                .iter()
+                .flat_map(|node| &node.path_model_idx)
+                .map(|&index| &self.path_models[index])
+                .filter(|model| model.render)
+                .flat_map(|model| &model.paths)
+                .flat_map(|path| &path.points)
+                .for_each(|point| {
+                    push_label(
+                        point.pos,
+                        point.index.to_string(),
+                        egui::Color32::from_rgb(255, 200, 120),
+                    )
+                });
+        }
+        if self.filter.spawn_render {
+            nodes
+                .iter()
+                .flat_map(|node| &node.spawn_model_idx)
+                .map(|&index| &self.spawn_models[index])
+                .filter(|model| model.render)
+                .flat_map(|model| &model.spawns)
+                .for_each(|spawn| push_label(spawn.pos, spawn.label(), egui::Color32::KHAKI));
+        }
+        if self.filter.camera_render {
+            nodes
+                .iter()
+                .flat_map(|node| &node.camera_model_idx)
+                .map(|&index| &self.camera_models[index])
+                .filter(|model| model.render)
+                .flat_map(|model| &model.cameras)
+                .for_each(|cam| push_label(cam.pos, cam.label(), egui::Color32::WHITE));
+        }
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lyse(story_flag: i16, night: u8, layer: u8) -> LYSE {
+        LYSE {
+            story_flag,
+            night,
+            layer,
+        }
+    }
+
+    #[test]
+    fn overlapping_lyse_entries_follow_the_order() {
+        let entries = [
+            lyse(-1, 0, 1),
+            lyse(5, 0, 2),
+            lyse(0x10, 0, 3),
+            lyse(5, 1, 4),
+        ];
+        let entries: Vec<_> = entries.iter().collect();
+        let mut state = StoryState {
+            story_flags: "5, 0x10".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(state.matching(&entries).len(), 3);
+        assert_eq!(state.layer(&entries), Some(3));
+        state.order = LyseOrder::FirstMatch;
+        assert_eq!(state.layer(&entries), Some(1));
+
+        // Only the night entry applies, whatever the order
+        state.night = true;
+        assert_eq!(state.layer(&entries), Some(4));
+        state.story_flags.clear();
+        assert_eq!(state.layer(&entries), None);
+    }
+
+    fn object(name: &str, dzb_names: &[&str]) -> ObjectArchive {
+        ObjectArchive {
+            name: name.to_string(),
+            dzb_names: dzb_names.iter().map(|dzb| dzb.to_string()).collect(),
+            dzb_model_idx: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn find_object_lookup_order() {
+        let objects = [
+            object("BigDoorA", &["BigDoorA"]),
+            object("Tubo", &["TuboBig"]),
+            object("TuboSmall", &["Small"]),
+            object("DoorB", &["Door"]),
+        ];
+        let find = |arcs: Option<&[String]>, actor: &str| {
+            find_object(&objects, arcs, actor).map(|object| object.name.as_str())
+        };
+
+        // The archive name wins over a collision file name, which wins over a partial name
+        assert_eq!(find(None, "tubo"), Some("Tubo"));
+        assert_eq!(find(None, "TuboBig"), Some("Tubo"));
+        assert_eq!(find(None, "Door"), Some("DoorB"));
+
+        // Partial names only match archives listed in ARCN
+        assert_eq!(find(None, "Big"), None);
+        let arcs = ["BigDoorA".to_string(), "TuboSmall".to_string()];
+        assert_eq!(find(Some(&arcs), "Big"), Some("BigDoorA"));
+        assert_eq!(find(Some(&arcs), "tubo"), Some("TuboSmall"));
+        assert_eq!(find(Some(&arcs), "DoorB"), None);
+    }
+
+    #[test]
+    fn nodes_with_only_a_bzs_are_kept() {
+        let mut scene = Scene::new();
+        assert!(scene.finish_node(SceneNode::default()).is_none());
+
+        let mut room = SceneNode::default().with_name("Room 0".to_string());
+        scene.add_bzs(&mut room, BZS::from_chunks(&[]));
+        let room = scene
+            .finish_node(room)
+            .expect("The bzs should keep the room");
+        assert!(room.render);
+        assert!(room.bzs.is_some());
+    }
+
+    #[test]
+    fn select_room_follows_the_room_links() {
+        // Room 0 loads room 1, room 2 loads nothing else
+        let rmpl = vec![0, 1, 0, 8, 2, 0, 0, 6, 0, 1];
+        let mut scene = Scene::new();
+        scene.root_node.bzs = Some(BZS::from_chunks(&[(b"RMPL", 2, rmpl)]));
+        scene.root_node.children = (0..3)
+            .map(|i| SceneNode::default().with_name(format!("Room {i}")))
+            .collect();
+        scene.link_room_visibility = true;
+
+        let states = |scene: &Scene| -> Vec<(RoomState, bool)> {
+            let rooms = &scene.root_node.children;
+            rooms
+                .iter()
+                .map(|room| (room.room_state, room.link_hidden))
+                .collect()
+        };
+
+        scene.select_room(Some(0));
+        assert_eq!(
+            states(&scene),
+            [
+                (RoomState::Selected, false),
+                (RoomState::Linked, false),
+                (RoomState::Unrelated, true),
+            ]
+        );
+
+        scene.select_room(Some(2));
+        assert_eq!(
+            states(&scene),
+            [
+                (RoomState::Unrelated, true),
+                (RoomState::Unrelated, true),
+                (RoomState::Selected, false),
+            ]
+        );
+
+        // Clearing the selection shows every room again
+        scene.select_room(None);
+        assert_eq!(states(&scene), [(RoomState::Unrelated, false); 3]);
     }
 }
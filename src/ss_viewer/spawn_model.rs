@@ -0,0 +1,154 @@
+use crate::{
+    file_formats::BZS,
+    gfx::{mesh::push_box, Mesh, Model, Shader, Vertex},
+};
+use eframe::glow;
+use glam::{Mat4, Vec3, Vec4};
+
+use super::actor_model::rotation_mtx;
+
+const SPAWN_COLOR: Vec4 = Vec4::new(1.0, 0.95, 0.3, 1.0);
+// Length of the arrow drawn for a spawn, the head makes up the last third
+const ARROW_LENGTH: f32 = 150.0;
+const ARROW_WIDTH: f32 = 30.0;
+
+/// A player spawn point (PLY) of a stage or room
+#[derive(Debug, Clone)]
+pub struct Spawn {
+    pub index: usize, // Index in the PLY list of its bzs
+    pub entrance_id: i16,
+    pub storyflag: i16,
+    pub play_cutscene: u8,
+    pub byte4: u8,
+    pub pos: Vec3,
+    pub angle: [i16; 3],
+}
+
+impl Spawn {
+    pub fn transform(&self) -> Mat4 {
+        Mat4::from_translation(self.pos) * rotation_mtx(self.angle)
+    }
+
+    /// The direction the player faces when spawning
+    pub fn forward(&self) -> Vec3 {
+        self.transform().transform_vector3(Vec3::Z)
+    }
+
+    pub fn label(&self) -> String {
+        format!("Entrance {}", self.entrance_id)
+    }
+
+    pub fn tooltip(&self) -> String {
+        format!(
+            "PLY {}\nstoryflag: {}\nplay_cutscene: {}\nbyte4: 0x{:02X}\npos: ({:.1}, {:.1}, {:.1})\nangle: (0x{:04X}, 0x{:04X}, 0x{:04X})",
+            self.index,
+            self.storyflag,
+            self.play_cutscene,
+            self.byte4,
+            self.pos.x,
+            self.pos.y,
+            self.pos.z,
+            self.angle[0] as u16,
+            self.angle[1] as u16,
+            self.angle[2] as u16,
+        )
+    }
+
+    // An arrow lying on the spawn, pointing in the facing direction (+Z)
+    fn build_verts(&self, verts: &mut Vec<Vertex>) {
+        let mtx = self.transform();
+        let shaft_length = ARROW_LENGTH * 2.0 / 3.0;
+        let height = ARROW_WIDTH / 2.0;
+        push_box(
+            verts,
+            &(mtx
+                * Mat4::from_translation(Vec3::new(0.0, height / 2.0, shaft_length / 2.0))
+                * Mat4::from_scale(Vec3::new(ARROW_WIDTH / 2.0, height, shaft_length))),
+            |_| SPAWN_COLOR,
+        );
+
+        // Pyramid for the head
+        let base = [
+            Vec3::new(-ARROW_WIDTH, 0.0, shaft_length),
+            Vec3::new(ARROW_WIDTH, 0.0, shaft_length),
+            Vec3::new(ARROW_WIDTH, height, shaft_length),
+            Vec3::new(-ARROW_WIDTH, height, shaft_length),
+        ];
+        let tip = Vec3::new(0.0, height / 2.0, ARROW_LENGTH);
+        let head_clr = SPAWN_COLOR.lerp(Vec4::ONE, 0.5);
+        let mut push_tri = |tri: [Vec3; 3], clr: Vec4| {
+            let nrm = (tri[1] - tri[0]).cross(tri[2] - tri[0]).normalize_or_zero();
+            let nrm = mtx.transform_vector3(nrm);
+            tri.iter()
+                .for_each(|&p| verts.push(Vertex::new(mtx.transform_point3(p), nrm, clr)));
+        };
+        for i in 0..base.len() {
+            push_tri([base[i], base[(i + 1) % base.len()], tip], head_clr);
+        }
+        push_tri([base[0], base[2], base[1]], SPAWN_COLOR);
+        push_tri([base[0], base[3], base[2]], SPAWN_COLOR);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnModel {
+    pub name: String,
+    pub spawns: Vec<Spawn>,
+
+    // Rendering Information
+    pub render: bool,
+    mesh: Mesh,
+}
+
+impl SpawnModel {
+    pub fn from_bzs(name: String, bzs: &BZS) -> Self {
+        let spawns: Vec<Spawn> = bzs
+            .ply()
+            .iter()
+            .enumerate()
+            .map(|(index, ply)| Spawn {
+                index,
+                entrance_id: ply.entrance_id,
+                storyflag: ply.storyflag,
+                play_cutscene: ply.play_cutscene,
+                byte4: ply.byte4,
+                pos: Vec3::from_array(ply.pos),
+                angle: ply.angle,
+            })
+            .collect();
+
+        let mut verts = Vec::new();
+        spawns
+            .iter()
+            .for_each(|spawn| spawn.build_verts(&mut verts));
+
+        Self {
+            name,
+            spawns,
+            render: true,
+            mesh: Mesh::new(verts, glow::TRIANGLES),
+        }
+    }
+}
+
+impl Model for SpawnModel {
+    fn setup_gl(&mut self, gl: &glow::Context) {
+        self.mesh.setup_gl(gl);
+    }
+
+    fn destroy_gl(&mut self, gl: &glow::Context) {
+        self.mesh.destroy_gl(gl);
+    }
+
+    fn update_gl(&mut self, gl: &glow::Context) {
+        self.mesh.update_gl(gl);
+    }
+
+    fn draw(&mut self, gl: &glow::Context, shader: &Shader) {
+        if !self.render {
+            return;
+        }
+
+        self.mesh.draw(gl, shader);
+    }
+}